@@ -1,7 +1,8 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{ed25519_program, sysvar::instructions as ix_sysvar};
 use anchor_spl::{
-    associated_token::AssociatedToken,
-    token::{self, Burn, FreezeAccount, Mint, MintTo, ThawAccount, Token, TokenAccount, Transfer},
+    associated_token::{get_associated_token_address, AssociatedToken},
+    token::{self, Burn, CloseAccount, FreezeAccount, Mint, MintTo, ThawAccount, Token, TokenAccount, Transfer},
 };
 // Optional Token-2022 support by feature flag. For PoC we stick to token2022 aliasing.
 #[cfg(feature = "token2022")]
@@ -12,8 +13,28 @@ declare_id!("programid");
 /* =============================== Constants ================================ */
 
 const MAX_ORACLES: usize = 8;
-const MAX_MILESTONES: usize = 10;
 const QUORUM_MIN: u8 = 1;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+const MAX_RETENTION_TRANCHES: usize = 4;
+const MAX_ARBITERS: usize = 5;
+/// `milestone_id` used in oracle attestations that sign off the whole delivery.
+const DELIVERY_ATTESTATION_ID: u16 = u16::MAX;
+/// Oracle attestation message: escrow (32) | project_id u64 LE (8) | milestone_id u16 LE (2)
+/// | evidence_hash (32) | expiry_ts i64 LE (8) | nonce u32 LE (4, the oracle's `oracle_nonces` entry).
+const ATTESTATION_MSG_LEN: usize = 32 + 8 + 2 + 32 + 8 + 4;
+/// Lifetime of an oracle vote cast directly by the oracle (attested votes carry their own expiry).
+const ORACLE_VOTE_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+/// Default for how long an oracle/deadline/change-order proposal stays acceptable by the
+/// counterparty (`Escrow.proposal_ttl_secs` overrides it per escrow).
+const PROPOSAL_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+/// Default for how long the respondent has to answer a dispute before arbiters may rule
+/// by default (`Config.dispute_response_secs` overrides it).
+const DISPUTE_RESPONSE_SECS: i64 = 7 * 24 * 60 * 60;
+/// How long the appeal arbiter has to decide an appeal; after that the appealed ruling stands.
+const APPEAL_DECISION_SECS: i64 = 14 * 24 * 60 * 60;
+/// Accounts per escrow in `process_timeouts` remaining_accounts:
+/// [escrow, vault_authority, vault_ata, buyer_ata].
+const TIMEOUT_ACCOUNTS_PER_ESCROW: usize = 4;
 
 /* ================================ Program ================================= */
 
@@ -30,9 +51,15 @@ pub mod construction_escrow {
         insurance_bps: u16,
         retention_bps: u16,
         warranty_days: i64,
+        min_warranty_days: i64,
+        max_warranty_days: i64,
         quorum_m: u8,
     ) -> Result<()> {
         require!(quorum_m >= QUORUM_MIN, EscrowError::BadQuorum);
+        require!(
+            0 <= min_warranty_days && min_warranty_days <= warranty_days && warranty_days <= max_warranty_days,
+            EscrowError::BadWarrantyPeriod
+        );
         let cfg = &mut ctx.accounts.config;
         cfg.authority = ctx.accounts.authority.key();
         cfg.treasury = ctx.accounts.treasury.key();
@@ -41,10 +68,25 @@ pub mod construction_escrow {
         cfg.insurance_bps = insurance_bps;
         cfg.retention_bps = retention_bps;
         cfg.warranty_days = warranty_days;
+        cfg.min_warranty_days = min_warranty_days;
+        cfg.max_warranty_days = max_warranty_days;
         cfg.quorum_m = quorum_m;
         cfg.arbiter = ctx.accounts.arbiter.key();
+        // Default dispute panel: the single arbiter, 1-of-1
+        cfg.arbiters = [Pubkey::default(); MAX_ARBITERS];
+        cfg.arbiters[0] = cfg.arbiter;
+        cfg.arbiters_len = 1;
+        cfg.arbiter_threshold = 1;
+        cfg.dispute_bond = 0;
+        cfg.dispute_bond_forfeit = BondForfeit::Counterparty as u8;
+        cfg.arbiter_fee_bps = 0;
+        cfg.arbiter_fee_payer = ArbiterFeePayer::Loser as u8;
+        cfg.appeal_window_secs = 0;
+        cfg.appeal_arbiter = Pubkey::default(); // appeals disabled
+        cfg.appeal_bond = 0;
         cfg.pending_authority = Pubkey::default();
         cfg.bump = ctx.bumps.config;
+        cfg.dispute_response_secs = 0;
         emit!(ConfigUpdated {
             fee_bps,
             insurance_bps,
@@ -69,6 +111,86 @@ pub mod construction_escrow {
         Ok(())
     }
 
+    /// Update the default warranty period and the per-escrow min/max bounds (days).
+    pub fn update_warranty_bounds(
+        ctx: Context<ConfigAuthority>,
+        warranty_days: i64,
+        min_warranty_days: i64,
+        max_warranty_days: i64,
+    ) -> Result<()> {
+        require!(
+            0 <= min_warranty_days && min_warranty_days <= warranty_days && warranty_days <= max_warranty_days,
+            EscrowError::BadWarrantyPeriod
+        );
+        let cfg = &mut ctx.accounts.config;
+        cfg.warranty_days = warranty_days;
+        cfg.min_warranty_days = min_warranty_days;
+        cfg.max_warranty_days = max_warranty_days;
+        emit!(WarrantyBoundsUpdated { warranty_days, min_warranty_days, max_warranty_days });
+        Ok(())
+    }
+
+    /// Replace the default dispute panel; `threshold` matching votes resolve a dispute.
+    /// Disputes already open keep the panel they were opened with.
+    pub fn set_arbiter_panel(ctx: Context<ConfigAuthority>, arbiters: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        validate_arbiter_panel(&arbiters, threshold)?;
+        let cfg = &mut ctx.accounts.config;
+        cfg.arbiters = [Pubkey::default(); MAX_ARBITERS];
+        for (i, pk) in arbiters.iter().enumerate() {
+            cfg.arbiters[i] = *pk;
+        }
+        cfg.arbiters_len = arbiters.len() as u8;
+        cfg.arbiter_threshold = threshold;
+        emit!(ArbiterPanelSet { project_id: None, arbiters, threshold });
+        Ok(())
+    }
+
+    /// Set the bond (in escrow mint units) a party deposits to open a dispute, and where a
+    /// losing claimant's bond goes.
+    pub fn set_dispute_bond(ctx: Context<ConfigAuthority>, amount: u64, forfeit_to: BondForfeit) -> Result<()> {
+        let cfg = &mut ctx.accounts.config;
+        cfg.dispute_bond = amount;
+        cfg.dispute_bond_forfeit = forfeit_to as u8;
+        emit!(DisputeBondSet { amount, forfeit_to });
+        Ok(())
+    }
+
+    /// Set the arbiter fee (bps of the disputed amount, paid to the panel arbiter whose vote
+    /// posted the ruling) and who bears it.
+    pub fn set_arbiter_fee(ctx: Context<ConfigAuthority>, fee_bps: u16, payer: ArbiterFeePayer) -> Result<()> {
+        require!(fee_bps <= 10_000, EscrowError::BadBps);
+        let cfg = &mut ctx.accounts.config;
+        cfg.arbiter_fee_bps = fee_bps;
+        cfg.arbiter_fee_payer = payer as u8;
+        emit!(ArbiterFeeSet { fee_bps, payer });
+        Ok(())
+    }
+
+    /// Set the appeal window after a ruling, the appeal arbiter (default key = no appeals)
+    /// and the appellant's bond.
+    pub fn set_appeal_terms(
+        ctx: Context<ConfigAuthority>,
+        window_secs: i64,
+        appeal_arbiter: Pubkey,
+        appeal_bond: u64,
+    ) -> Result<()> {
+        require!(window_secs >= 0, EscrowError::BadAppealWindow);
+        let cfg = &mut ctx.accounts.config;
+        cfg.appeal_window_secs = window_secs;
+        cfg.appeal_arbiter = appeal_arbiter;
+        cfg.appeal_bond = appeal_bond;
+        emit!(AppealTermsSet { window_secs, appeal_arbiter, appeal_bond });
+        Ok(())
+    }
+
+    /// Set how long a respondent has to answer new disputes (0 = `DISPUTE_RESPONSE_SECS`).
+    pub fn set_dispute_response_window(ctx: Context<ConfigAuthority>, response_secs: i64) -> Result<()> {
+        require!(response_secs >= 0, EscrowError::BadResponseWindow);
+        ctx.accounts.config.dispute_response_secs = response_secs;
+        emit!(DisputeResponseWindowSet { response_secs });
+        Ok(())
+    }
+
     pub fn transfer_market_authority_propose(ctx: Context<ConfigAuthority>, new_auth: Pubkey) -> Result<()> {
         let cfg = &mut ctx.accounts.config;
         cfg.pending_authority = new_auth;
@@ -88,23 +210,31 @@ pub mod construction_escrow {
     /* ------------------------------ Create Escrow -------------------------- */
 
     /// Create escrow and move buyer funds (quote tokens) into PDA vault.
-    /// `oracles` length <= MAX_ORACLES; quorum_m >= 1.
+    /// `oracles` length <= MAX_ORACLES, each with a non-zero weight in `oracle_weights`;
+    /// verification needs live votes totalling at least `quorum_weight`.
     /// `price_snapshot_1e6` lets you store optional USD notional (6dp). Set to 0 if unused.
+    /// `warranty_days` must lie within the Config min/max bounds; the warranty clock
+    /// starts at delivery verification (or final release), not at creation.
     pub fn create_escrow(
         ctx: Context<CreateEscrow>,
         project_id: u64,
         amount: u64,
         ix_nonce: u64,
         oracles: Vec<Pubkey>,
-        quorum_m: u8,
+        oracle_weights: Vec<u16>,
+        quorum_weight: u32,
         price_snapshot_1e6: u64,
         nft_enabled: bool,
+        warranty_days: i64,
     ) -> Result<()> {
         require!(amount > 0, EscrowError::ZeroAmount);
-        require!(quorum_m >= QUORUM_MIN, EscrowError::BadQuorum);
-        require!(oracles.len() <= MAX_ORACLES, EscrowError::TooManyOracles);
+        validate_oracle_set(&oracles, &oracle_weights, quorum_weight)?;
 
         let cfg = &ctx.accounts.config;
+        require!(
+            cfg.min_warranty_days <= warranty_days && warranty_days <= cfg.max_warranty_days,
+            EscrowError::BadWarrantyPeriod
+        );
 
         // Record state
         let escrow = &mut ctx.accounts.escrow;
@@ -120,17 +250,22 @@ pub mod construction_escrow {
         escrow.fee_bps = cfg.fee_bps;
         escrow.insurance_bps = cfg.insurance_bps;
         escrow.retention_bps = cfg.retention_bps;
+        escrow.late_penalty_bps = 0;
+        escrow.late_penalty_cap_bps = 0;
+        escrow.late_grace_secs = 0;
 
         escrow.amount = amount;
         escrow.vault_bump = ctx.bumps.vault_authority;
         escrow.bump = ctx.bumps.escrow;
 
         // Oracles / quorum
-        escrow.quorum_m = quorum_m;
+        escrow.quorum_weight = quorum_weight;
         escrow.oracles_len = oracles.len() as u8;
         escrow.oracles = [Pubkey::default(); MAX_ORACLES];
+        escrow.oracle_weights = [0u16; MAX_ORACLES];
         for (i, pk) in oracles.iter().enumerate() {
             escrow.oracles[i] = *pk;
+            escrow.oracle_weights[i] = oracle_weights[i];
         }
 
         // Price snapshot
@@ -141,21 +276,69 @@ pub mod construction_escrow {
         escrow.created_ts = Clock::get()?.unix_timestamp;
         escrow.verified_ts = 0;
         escrow.released_ts = 0;
-        escrow.warranty_end_ts = escrow.created_ts + cfg.warranty_days * 24 * 60 * 60;
+        escrow.warranty_secs = warranty_days * SECONDS_PER_DAY;
+        escrow.warranty_end_ts = 0; // set when the warranty starts
         escrow.verify_by_ts = 0;
         escrow.deliver_by_ts = 0;
         escrow.in_progress = false;
         escrow.in_transfer = false;
         escrow.retention_released = false;
-
-        // Milestones init
-        escrow.milestones_len = 0;
-        escrow.milestones = [Milestone::EMPTY; MAX_MILESTONES];
+        escrow.retention_held = 0;
+        escrow.retention_paid = 0;
+        escrow.retention_tranches_len = 0;
+        escrow.retention_tranche_ts = [0i64; MAX_RETENTION_TRANCHES];
+        escrow.retention_tranche_bps = [0u16; MAX_RETENTION_TRANCHES];
+        escrow.retention_tranches_released = 0;
+
+        // Warranty claims
+        escrow.warranty_claim_status = WarrantyClaimStatus::None as u8;
+        escrow.warranty_claim_amount = 0;
+        escrow.warranty_claim_hash = [0u8; 32];
+        escrow.warranty_claims_count = 0;
+        escrow.retention_frozen = 0;
+        escrow.retention_claimed = 0;
+
+        // No pending two-party changes
+        clear_oracles_proposal(escrow);
+        clear_deadlines_proposal(escrow);
+        escrow.proposal_ttl_secs = 0;
+
+        // Change orders
+        escrow.change_order_count = 0;
+        escrow.last_change_order_hash = [0u8; 32];
+        clear_change_order(escrow);
+
+        // Milestones live in their own PDAs; track count + running total here
+        escrow.milestones_count = 0;
+        escrow.milestones_total = 0;
+        escrow.milestones_share_bps = 0;
+        escrow.milestones_released_share_bps = 0;
+        escrow.milestones_released_amount = 0;
 
         // Evidence counters
         escrow.attestations_count = 0;
+
+        // Oracle votes (bitmap over oracle indices)
+        escrow.delivery_votes = 0;
+        escrow.delivery_vote_expiry = [0i64; MAX_ORACLES];
+        escrow.delivery_vote_hash = [0u8; 32];
+        escrow.oracle_set_version = 0;
+        escrow.oracle_nonces = [0u32; MAX_ORACLES];
+        escrow.delivery_rejections = 0;
+        escrow.veto_weight = 0;
+        escrow.escalated_from_state = 0;
         escrow.cancel_requested_by = Pubkey::default();
         escrow.dispute_open = false;
+        escrow.disputes_count = 0;
+        escrow.dispute_milestone_scoped = false;
+        escrow.dispute_milestone_id = 0;
+        escrow.dispute_frozen = 0;
+        clear_dispute_votes(escrow);
+
+        // Arbiter panel override (empty = use the Config panel)
+        escrow.arbiters = [Pubkey::default(); MAX_ARBITERS];
+        escrow.arbiters_len = 0;
+        escrow.arbiter_threshold = 0;
 
         // Optional receipt NFT toggle
         escrow.nft_enabled = nft_enabled;
@@ -182,7 +365,7 @@ pub mod construction_escrow {
             seller: escrow.seller,
             mint: escrow.mint,
             amount,
-            quorum_m,
+            quorum_weight,
             price_snapshot_1e6
         });
 
@@ -191,12 +374,87 @@ pub mod construction_escrow {
 
     /* -------------------------- Deadlines & Liveness ----------------------- */
 
-    pub fn set_deadlines(ctx: Context<BuyerOrSeller>, verify_by_ts: i64, deliver_by_ts: i64) -> Result<()> {
+    /// Propose new deadlines; they only take effect once the counterparty accepts.
+    pub fn propose_deadlines(ctx: Context<BuyerOrSeller>, verify_by_ts: i64, deliver_by_ts: i64) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(e.state == EscrowState::Open as u8 || e.state == EscrowState::PartiallyReleased as u8, EscrowError::BadState);
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            e.deadlines_proposed_by == Pubkey::default() || now > e.deadlines_proposal_expires_ts,
+            EscrowError::ProposalPending
+        );
+
+        e.pending_verify_by_ts = verify_by_ts;
+        e.pending_deliver_by_ts = deliver_by_ts;
+        e.deadlines_proposed_by = ctx.accounts.actor.key();
+        e.deadlines_proposal_expires_ts = proposal_expiry(e, now);
+
+        emit!(DeadlinesProposed {
+            project_id: e.project_id,
+            by: e.deadlines_proposed_by,
+            verify_by_ts,
+            deliver_by_ts,
+            expires_ts: e.deadlines_proposal_expires_ts
+        });
+        Ok(())
+    }
+
+    /// Counterparty countersigns the pending deadlines.
+    pub fn accept_deadlines(ctx: Context<BuyerOrSeller>) -> Result<()> {
         let e = &mut ctx.accounts.escrow;
+        let caller = ctx.accounts.actor.key();
+        require!(e.deadlines_proposed_by != Pubkey::default(), EscrowError::NoPendingProposal);
+        require!(caller != e.deadlines_proposed_by, EscrowError::Unauthorized);
+        require!(Clock::get()?.unix_timestamp <= e.deadlines_proposal_expires_ts, EscrowError::ProposalExpired);
         require!(e.state == EscrowState::Open as u8 || e.state == EscrowState::PartiallyReleased as u8, EscrowError::BadState);
-        e.verify_by_ts = verify_by_ts;
-        e.deliver_by_ts = deliver_by_ts;
-        emit!(DeadlinesSet { project_id: e.project_id, verify_by_ts, deliver_by_ts });
+
+        e.verify_by_ts = e.pending_verify_by_ts;
+        e.deliver_by_ts = e.pending_deliver_by_ts;
+        clear_deadlines_proposal(e);
+
+        emit!(ProposalAccepted { project_id: e.project_id, kind: ChangeKind::Deadlines, by: caller });
+        emit!(DeadlinesSet { project_id: e.project_id, verify_by_ts: e.verify_by_ts, deliver_by_ts: e.deliver_by_ts });
+        Ok(())
+    }
+
+    /// Counterparty rejects (or proposer withdraws) the pending deadlines.
+    pub fn reject_deadlines(ctx: Context<BuyerOrSeller>) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(e.deadlines_proposed_by != Pubkey::default(), EscrowError::NoPendingProposal);
+        clear_deadlines_proposal(e);
+        emit!(ProposalRejected { project_id: e.project_id, kind: ChangeKind::Deadlines, by: ctx.accounts.actor.key() });
+        Ok(())
+    }
+
+    /// Both parties set how long future proposals stay open (0 = `PROPOSAL_TTL_SECS`).
+    pub fn set_proposal_ttl(ctx: Context<BuyerAndSeller>, ttl_secs: i64) -> Result<()> {
+        require!(ttl_secs >= 0, EscrowError::BadProposalTtl);
+        let e = &mut ctx.accounts.escrow;
+        e.proposal_ttl_secs = ttl_secs;
+        emit!(ProposalTtlSet { project_id: e.project_id, ttl_secs });
+        Ok(())
+    }
+
+    /// Both parties agree a liquidated-damages schedule for late delivery: after
+    /// `deliver_by_ts + grace_secs`, `bps_per_day` accrues per started day, capped at `cap_bps`.
+    /// The cap must cover at least one day's accrual; `bps_per_day = 0` disables the penalty.
+    pub fn set_late_penalty(ctx: Context<BuyerAndSeller>, grace_secs: i64, bps_per_day: u16, cap_bps: u16) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(
+            e.state == EscrowState::Open as u8
+                || e.state == EscrowState::Verified as u8
+                || e.state == EscrowState::PartiallyReleased as u8,
+            EscrowError::BadState
+        );
+        require!(grace_secs >= 0, EscrowError::BadGracePeriod);
+        require!(bps_per_day <= 10_000 && cap_bps <= 10_000, EscrowError::BadBps);
+        require!(bps_per_day == 0 || cap_bps >= bps_per_day, EscrowError::BadBps);
+
+        e.late_grace_secs = grace_secs;
+        e.late_penalty_bps = bps_per_day;
+        e.late_penalty_cap_bps = cap_bps;
+
+        emit!(LatePenaltySet { project_id: e.project_id, grace_secs, bps_per_day, cap_bps });
         Ok(())
     }
 
@@ -213,6 +471,7 @@ pub mod construction_escrow {
         let now = Clock::get()?.unix_timestamp;
         require!(e.verify_by_ts > 0 && now > e.verify_by_ts, EscrowError::NotExpired);
         require!(e.state == EscrowState::Open as u8, EscrowError::BadState);
+        require!(!e.dispute_open, EscrowError::DisputeAlreadyOpen);
 
         // Transfer back to buyer
         let refund_amount = ctx.accounts.vault_ata.amount;
@@ -234,127 +493,495 @@ pub mod construction_escrow {
         Ok(())
     }
 
-    /* ---------------------------- Verification ----------------------------- */
+    /* ----------------------------- Change Orders ---------------------------- */
 
-    /// M-of-N oracle quorum verification. Pass any number of signer accounts
-    /// in remaining_accounts; we’ll count signers that are in `escrow.oracles`.
-    pub fn verify_delivery(ctx: Context<VerifyWithQuorum>, project_id: u64) -> Result<()> {
+    /// Either party submits a change order setting a new contract amount, anchored
+    /// by the hash of the signed change-order document.
+    pub fn submit_change_order(ctx: Context<BuyerOrSeller>, new_amount: u64, doc_hash: [u8; 32]) -> Result<()> {
         let e = &mut ctx.accounts.escrow;
-        require!(e.project_id == project_id, EscrowError::ProjectMismatch);
-        require!(e.state == EscrowState::Open as u8 || e.state == EscrowState::PartiallyReleased as u8, EscrowError::BadState);
+        require!(
+            e.state == EscrowState::Open as u8
+                || e.state == EscrowState::Verified as u8
+                || e.state == EscrowState::PartiallyReleased as u8,
+            EscrowError::BadState
+        );
+        require!(!e.dispute_open, EscrowError::DisputeAlreadyOpen);
+        require!(new_amount > 0, EscrowError::ZeroAmount);
+        require!(new_amount != e.amount, EscrowError::ChangeOrderNoop);
+
+        require!(e.scheduled_total_for(new_amount) <= new_amount, EscrowError::MilestoneOverTotal);
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            e.change_order_proposed_by == Pubkey::default() || now > e.change_order_expires_ts,
+            EscrowError::ProposalPending
+        );
+
+        e.pending_change_order_amount = new_amount;
+        e.pending_change_order_hash = doc_hash;
+        e.change_order_proposed_by = ctx.accounts.actor.key();
+        e.change_order_expires_ts = proposal_expiry(e, now);
+
+        emit!(ChangeOrderSubmitted {
+            project_id: e.project_id,
+            by: e.change_order_proposed_by,
+            change_order_no: e.change_order_count + 1,
+            old_amount: e.amount,
+            new_amount,
+            doc_hash,
+            expires_ts: e.change_order_expires_ts
+        });
+        Ok(())
+    }
 
-        let votes = count_quorum_votes(e, &ctx.remaining_accounts)?;
-        require!((votes as u8) >= e.quorum_m, EscrowError::QuorumNotMet);
+    /// Buyer and seller both sign to apply the pending change order. An increase pulls the
+    /// difference from the buyer ATA into the vault; a decrease refunds it to the buyer.
+    pub fn approve_change_order(ctx: Context<ApproveChangeOrder>, doc_hash: [u8; 32]) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(e.change_order_proposed_by != Pubkey::default(), EscrowError::NoPendingProposal);
+        require!(e.pending_change_order_hash == doc_hash, EscrowError::ChangeOrderMismatch);
+        require!(Clock::get()?.unix_timestamp <= e.change_order_expires_ts, EscrowError::ProposalExpired);
+        require!(
+            e.state == EscrowState::Open as u8
+                || e.state == EscrowState::Verified as u8
+                || e.state == EscrowState::PartiallyReleased as u8,
+            EscrowError::BadState
+        );
+        require!(!e.dispute_open, EscrowError::DisputeAlreadyOpen);
 
-        if e.state == EscrowState::Open as u8 {
-            e.state = EscrowState::Verified as u8;
+        let old_amount = e.amount;
+        let new_amount = e.pending_change_order_amount;
+        require!(e.scheduled_total_for(new_amount) <= new_amount, EscrowError::MilestoneOverTotal);
+
+        if new_amount > old_amount {
+            // Top up: buyer → vault
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.buyer_ata.to_account_info(),
+                to: ctx.accounts.vault_ata.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+            token::transfer(cpi_ctx, new_amount - old_amount)?;
+        } else {
+            // Reduction: vault → buyer
+            let refund = old_amount - new_amount;
+            require!(
+                ctx.accounts.vault_ata.amount.saturating_sub(e.retention_held) >= refund,
+                EscrowError::VaultBalanceLow
+            );
+            transfer_from_vault(
+                e,
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault_authority,
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.buyer_ata,
+                refund,
+            )?;
         }
-        e.verified_ts = Clock::get()?.unix_timestamp;
 
-        emit!(DeliveryVerified {
-            project_id,
-            quorum_votes: votes as u8,
-            when: e.verified_ts
+        e.amount = new_amount;
+        e.change_order_count = e.change_order_count.saturating_add(1);
+        e.last_change_order_hash = doc_hash;
+        clear_change_order(e);
+
+        emit!(ChangeOrderApplied {
+            project_id: e.project_id,
+            change_order_no: e.change_order_count,
+            old_amount,
+            new_amount,
+            retention: calc_retention(new_amount, e.retention_bps),
+            doc_hash
         });
+        Ok(())
+    }
+
+    /// Counterparty rejects (or proposer withdraws) the pending change order.
+    pub fn reject_change_order(ctx: Context<BuyerOrSeller>) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(e.change_order_proposed_by != Pubkey::default(), EscrowError::NoPendingProposal);
+        clear_change_order(e);
+        emit!(ProposalRejected { project_id: e.project_id, kind: ChangeKind::ChangeOrder, by: ctx.accounts.actor.key() });
+        Ok(())
+    }
+
+    /* ---------------------------- Verification ----------------------------- */
+
+    /// Relay oracle votes on the delivery. Oracles sign an attestation message offline
+    /// (see `ATTESTATION_MSG_LEN`, milestone_id = `DELIVERY_ATTESTATION_ID`) and a relayer
+    /// submits them as Ed25519 program instructions in the same transaction. Votes are
+    /// stored on the escrow until they expire; delivery is verified once live votes reach `quorum_weight`.
+    pub fn verify_delivery(ctx: Context<VerifyWithQuorum>, project_id: u64, evidence_hash: [u8; 32]) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(e.project_id == project_id, EscrowError::ProjectMismatch);
+
+        let now = Clock::get()?.unix_timestamp;
+        let (bits, expiry) =
+            collect_attestation_votes(e, &ctx.accounts.instructions, DELIVERY_ATTESTATION_ID, &evidence_hash, now)?;
+        require!(bits != 0, EscrowError::NoOracleVote);
+        record_delivery_votes(e, bits, &expiry, &evidence_hash, now)
+    }
+
+    /// An oracle votes directly on the delivery (vote lives `ORACLE_VOTE_TTL_SECS`).
+    pub fn vote_delivery(ctx: Context<OracleVote>, evidence_hash: [u8; 32]) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        let idx = oracle_index(e, &ctx.accounts.oracle.key()).ok_or(EscrowError::NotOracle)?;
+        let now = Clock::get()?.unix_timestamp;
+        let mut expiry = [0i64; MAX_ORACLES];
+        expiry[idx] = now.saturating_add(ORACLE_VOTE_TTL_SECS);
+        record_delivery_votes(e, 1 << idx, &expiry, &evidence_hash, now)
+    }
 
+    /// An oracle withdraws its delivery vote before quorum is reached. Its nonce is bumped,
+    /// so attestations it signed earlier can no longer be relayed to restore the vote.
+    pub fn withdraw_delivery_vote(ctx: Context<OracleVote>) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        let idx = oracle_index(e, &ctx.accounts.oracle.key()).ok_or(EscrowError::NotOracle)?;
+        require!(e.delivery_votes & (1 << idx) != 0, EscrowError::NoOracleVote);
+        e.delivery_votes &= !(1 << idx);
+        e.delivery_vote_expiry[idx] = 0;
+        e.oracle_nonces[idx] = e.oracle_nonces[idx].wrapping_add(1);
+        emit!(OracleVoteWithdrawn {
+            project_id: e.project_id,
+            milestone_id: None,
+            oracle: ctx.accounts.oracle.key(),
+            nonce: e.oracle_nonces[idx]
+        });
         Ok(())
     }
 
     /* ----------------------------- Milestones ------------------------------ */
 
-    pub fn add_milestone(ctx: Context<BuyerOrSeller>, amount: u64, evidence_hash: [u8; 32]) -> Result<()> {
+    /// Add a milestone PDA (`[b"milestone", escrow, id]`) with its own delivery deadline
+    /// (`due_ts`, 0 = fall back to the escrow-wide `deliver_by_ts`) and late penalty schedule.
+    /// Pass either a fixed `amount` or a `share_bps` of the contract value (not both).
+    pub fn add_milestone(
+        ctx: Context<AddMilestone>,
+        amount: u64,
+        share_bps: u16,
+        evidence_hash: [u8; 32],
+        due_ts: i64,
+        penalty_kind: PenaltyKind,
+        penalty_bps: u16,
+        penalty_cap_bps: u16,
+    ) -> Result<()> {
         let e = &mut ctx.accounts.escrow;
         require!(e.state == EscrowState::Open as u8 || e.state == EscrowState::Verified as u8, EscrowError::BadState);
-        require!((e.milestones_len as usize) < MAX_MILESTONES, EscrowError::TooManyMilestones);
+        require!(penalty_bps <= 10_000 && penalty_cap_bps <= 10_000, EscrowError::BadBps);
+        // A PerDay cap below one day's accrual would silently disable the penalty
+        require!(
+            penalty_kind != PenaltyKind::PerDay || penalty_cap_bps >= penalty_bps,
+            EscrowError::BadBps
+        );
+        require!(share_bps == 0 || amount == 0, EscrowError::BadMilestoneAmount);
+
+        // Ensure shares <= 100% and milestone sum <= total amount (retain room for retention if desired)
+        let new_shares = e.milestones_share_bps as u32 + share_bps as u32;
+        require!(new_shares <= 10_000, EscrowError::MilestoneSharesOver);
+        e.milestones_total = e.milestones_total.saturating_add(amount);
+        e.milestones_share_bps = new_shares as u16;
+        require!(e.scheduled_total_for(e.amount) <= e.amount, EscrowError::MilestoneOverTotal);
+
+        let id = e.milestones_count;
+        let m = &mut ctx.accounts.milestone;
+        m.escrow = e.key();
+        m.id = id;
+        m.amount = amount;
+        m.share_bps = share_bps;
+        m.verified = false;
+        m.released = false;
+        m.verify_ts = 0;
+        m.evidence_hash = evidence_hash;
+        m.due_ts = due_ts;
+        m.penalty_kind = penalty_kind as u8;
+        m.penalty_bps = penalty_bps;
+        m.penalty_cap_bps = penalty_cap_bps;
+        m.disputed = false;
+        m.votes = 0;
+        m.vote_expiry = [0i64; MAX_ORACLES];
+        m.rejections = 0;
+        m.votes_oracle_set = e.oracle_set_version;
+        m.bump = ctx.bumps.milestone;
+        m.escalated = false;
+
+        e.milestones_count = id.checked_add(1).ok_or(EscrowError::TooManyMilestones)?;
+
+        emit!(MilestoneAdded { project_id: e.project_id, id, amount, share_bps, evidence_hash, due_ts });
+        Ok(())
+    }
+
+    /// Correct an unverified, unreleased milestone's amount/share or evidence hash (both parties sign).
+    pub fn update_milestone(
+        ctx: Context<EditMilestone>,
+        milestone_id: u16,
+        amount: u64,
+        share_bps: u16,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        let m = &mut ctx.accounts.milestone;
+        require!(
+            e.state == EscrowState::Open as u8
+                || e.state == EscrowState::Verified as u8
+                || e.state == EscrowState::PartiallyReleased as u8,
+            EscrowError::BadState
+        );
+        require!(!m.verified, EscrowError::AlreadyVerified);
+        require!(!m.released, EscrowError::MilestoneAlreadyReleased); // e.g. settled by a scoped ruling
+        require!(!m.disputed, EscrowError::MilestoneDisputed);
+        require!(share_bps == 0 || amount == 0, EscrowError::BadMilestoneAmount);
+
+        let new_shares = e.milestones_share_bps as u32 - m.share_bps as u32 + share_bps as u32;
+        require!(new_shares <= 10_000, EscrowError::MilestoneSharesOver);
+        e.milestones_total = e.milestones_total.saturating_sub(m.amount).saturating_add(amount);
+        e.milestones_share_bps = new_shares as u16;
+        require!(e.scheduled_total_for(e.amount) <= e.amount, EscrowError::MilestoneOverTotal);
+
+        let old_amount = m.amount;
+        m.amount = amount;
+        m.share_bps = share_bps;
+        if m.evidence_hash != evidence_hash {
+            // votes and rejections were cast on the old evidence
+            m.votes = 0;
+            m.rejections = 0;
+            clear_milestone_escalation(e, m);
+        }
+        m.evidence_hash = evidence_hash;
 
-        // Ensure milestone sum <= total amount (retain room for retention if desired)
-        let current_sum: u64 = e.milestones().iter().map(|m| m.amount).sum();
-        require!(current_sum.saturating_add(amount) <= e.amount, EscrowError::MilestoneOverTotal);
+        emit!(MilestoneUpdated {
+            project_id: e.project_id,
+            id: milestone_id,
+            old_amount,
+            new_amount: amount,
+            share_bps,
+            evidence_hash
+        });
+        Ok(())
+    }
 
-        let id = e.milestones_len;
-        e.milestones[id as usize] = Milestone {
-            id,
-            amount,
-            verified: false,
-            released: false,
-            verify_ts: 0,
+    /// Remove an unverified, unreleased milestone (both parties sign); rent goes back to the buyer.
+    pub fn remove_milestone(ctx: Context<RemoveMilestone>, milestone_id: u16) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        let m = &ctx.accounts.milestone;
+        require!(
+            e.state == EscrowState::Open as u8
+                || e.state == EscrowState::Verified as u8
+                || e.state == EscrowState::PartiallyReleased as u8,
+            EscrowError::BadState
+        );
+        require!(!m.verified, EscrowError::AlreadyVerified);
+        require!(!m.released, EscrowError::MilestoneAlreadyReleased); // e.g. settled by a scoped ruling
+        require!(!m.disputed, EscrowError::MilestoneDisputed);
+
+        e.milestones_total = e.milestones_total.saturating_sub(m.amount);
+        e.milestones_share_bps = e.milestones_share_bps.saturating_sub(m.share_bps);
+
+        emit!(MilestoneRemoved { project_id: e.project_id, id: milestone_id, amount: m.amount });
+        Ok(())
+    }
+
+    /// Relay Ed25519 oracle attestations over a milestone's `evidence_hash`. Votes are
+    /// stored on the milestone; it is verified once live votes reach `quorum_weight`.
+    pub fn verify_milestone(ctx: Context<VerifyMilestone>, milestone_id: u16) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        let m = &mut ctx.accounts.milestone;
+
+        let now = Clock::get()?.unix_timestamp;
+        let (bits, expiry) = collect_attestation_votes(e, &ctx.accounts.instructions, milestone_id, &m.evidence_hash, now)?;
+        require!(bits != 0, EscrowError::NoOracleVote);
+        record_milestone_votes(e, m, bits, &expiry, now)
+    }
+
+    /// An oracle votes directly on a milestone (vote lives `ORACLE_VOTE_TTL_SECS`).
+    pub fn vote_milestone(ctx: Context<OracleMilestoneVote>, _milestone_id: u16) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        let m = &mut ctx.accounts.milestone;
+        let idx = oracle_index(e, &ctx.accounts.oracle.key()).ok_or(EscrowError::NotOracle)?;
+        let now = Clock::get()?.unix_timestamp;
+        let mut expiry = [0i64; MAX_ORACLES];
+        expiry[idx] = now.saturating_add(ORACLE_VOTE_TTL_SECS);
+        record_milestone_votes(e, m, 1 << idx, &expiry, now)
+    }
+
+    /// An oracle withdraws its milestone vote before quorum is reached. As with delivery
+    /// votes its nonce is bumped, voiding every attestation it signed earlier.
+    pub fn withdraw_milestone_vote(ctx: Context<OracleMilestoneVote>, milestone_id: u16) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        let m = &mut ctx.accounts.milestone;
+        let idx = oracle_index(e, &ctx.accounts.oracle.key()).ok_or(EscrowError::NotOracle)?;
+        sync_milestone_votes(e, m);
+        require!(m.votes & (1 << idx) != 0, EscrowError::NoOracleVote);
+        m.votes &= !(1 << idx);
+        m.vote_expiry[idx] = 0;
+        e.oracle_nonces[idx] = e.oracle_nonces[idx].wrapping_add(1);
+        emit!(OracleVoteWithdrawn {
+            project_id: e.project_id,
+            milestone_id: Some(milestone_id),
+            oracle: ctx.accounts.oracle.key(),
+            nonce: e.oracle_nonces[idx]
+        });
+        Ok(())
+    }
+
+    /// An oracle records a failed delivery inspection. Its approval (if any) is dropped.
+    /// While rejections weigh at least `veto_weight` the delivery cannot be verified;
+    /// once they reach `quorum_weight` the escrow escalates to `Dispute` (the prior state
+    /// comes back if rejections are cleared before a dispute is opened).
+    pub fn reject_delivery(ctx: Context<OracleVote>, reason_code: u16, evidence_hash: [u8; 32]) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(e.state == EscrowState::Open as u8 || e.state == EscrowState::PartiallyReleased as u8, EscrowError::BadState);
+        require!(e.verified_ts == 0, EscrowError::AlreadyVerified);
+        let oracle = ctx.accounts.oracle.key();
+        let idx = oracle_index(e, &oracle).ok_or(EscrowError::NotOracle)?;
+        let before = rejection_weight(e, e.delivery_rejections);
+        e.delivery_votes &= !(1 << idx);
+        e.delivery_rejections |= 1 << idx;
+
+        let rejected = rejection_weight(e, e.delivery_rejections);
+        emit!(OracleRejected {
+            project_id: e.project_id,
+            milestone_id: None,
+            oracle,
+            reason_code,
             evidence_hash,
-            reserved: [0u8; 7],
-        };
-        e.milestones_len += 1;
+            rejection_weight: rejected
+        });
+        escalate_on_rejections(e, None, before, rejected);
+        Ok(())
+    }
 
-        emit!(MilestoneAdded { project_id: e.project_id, id, amount, evidence_hash });
+    /// An oracle clears its delivery rejection (e.g. defects fixed); verification resumes, and
+    /// an escalation without an opened dispute is undone once rejections fall below quorum.
+    pub fn clear_delivery_rejection(ctx: Context<OracleVote>) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        let idx = oracle_index(e, &ctx.accounts.oracle.key()).ok_or(EscrowError::NotOracle)?;
+        require!(e.delivery_rejections & (1 << idx) != 0, EscrowError::NoOracleRejection);
+        e.delivery_rejections &= !(1 << idx);
+        emit!(OracleRejectionCleared { project_id: e.project_id, milestone_id: None, oracle: ctx.accounts.oracle.key() });
+        restore_after_escalation(e);
+
+        if e.verified_ts == 0 && (e.state == EscrowState::Open as u8 || e.state == EscrowState::PartiallyReleased as u8) {
+            let now = Clock::get()?.unix_timestamp;
+            settle_delivery_votes(e, now);
+        }
         Ok(())
     }
 
-    pub fn verify_milestone(ctx: Context<VerifyWithQuorum>, milestone_id: u8) -> Result<()> {
+    /// An oracle records a failed milestone inspection. Same veto rule as `reject_delivery`;
+    /// reaching `quorum_weight` escalates only the milestone: it cannot be verified until
+    /// rejections are cleared, and either party may open a dispute scoped to it.
+    pub fn reject_milestone(
+        ctx: Context<OracleMilestoneVote>,
+        milestone_id: u16,
+        reason_code: u16,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
         let e = &mut ctx.accounts.escrow;
-        require!((milestone_id as usize) < e.milestones_len as usize, EscrowError::BadMilestoneId);
-
-        let votes = count_quorum_votes(e, &ctx.remaining_accounts)?;
-        require!((votes as u8) >= e.quorum_m, EscrowError::QuorumNotMet);
-
-        // Cache from `e` before mut borrow
-        let project_id = e.project_id;
-        let was_open = e.state == EscrowState::Open as u8;
-
-        // Scope the mutable borrow for milestone
-        let when: i64 = {
-            let m = &mut e.milestones[milestone_id as usize];
-            require!(!m.verified, EscrowError::AlreadyVerified);
-            m.verified = true;
-            m.verify_ts = Clock::get()?.unix_timestamp;
-            m.verify_ts
-        };
+        let m = &mut ctx.accounts.milestone;
+        require!(
+            e.state == EscrowState::Open as u8
+                || e.state == EscrowState::Verified as u8
+                || e.state == EscrowState::PartiallyReleased as u8,
+            EscrowError::BadState
+        );
+        require!(!m.verified, EscrowError::AlreadyVerified);
+        let oracle = ctx.accounts.oracle.key();
+        let idx = oracle_index(e, &oracle).ok_or(EscrowError::NotOracle)?;
+        sync_milestone_votes(e, m);
+        let before = rejection_weight(e, m.rejections);
+        m.votes &= !(1 << idx);
+        m.rejections |= 1 << idx;
+
+        let rejected = rejection_weight(e, m.rejections);
+        emit!(OracleRejected {
+            project_id: e.project_id,
+            milestone_id: Some(milestone_id),
+            oracle,
+            reason_code,
+            evidence_hash,
+            rejection_weight: rejected
+        });
+        escalate_on_rejections(e, Some(&mut **m), before, rejected);
+        Ok(())
+    }
 
-        if (was_open) {
-            e.state = EscrowState::Verified as u8;
+    /// An oracle clears its milestone rejection; verification resumes.
+    pub fn clear_milestone_rejection(ctx: Context<OracleMilestoneVote>, milestone_id: u16) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        let m = &mut ctx.accounts.milestone;
+        let idx = oracle_index(e, &ctx.accounts.oracle.key()).ok_or(EscrowError::NotOracle)?;
+        sync_milestone_votes(e, m);
+        require!(m.rejections & (1 << idx) != 0, EscrowError::NoOracleRejection);
+        m.rejections &= !(1 << idx);
+        emit!(OracleRejectionCleared { project_id: e.project_id, milestone_id: Some(milestone_id), oracle: ctx.accounts.oracle.key() });
+        clear_milestone_escalation(e, m);
+
+        if !m.verified {
+            let now = Clock::get()?.unix_timestamp;
+            settle_milestone_votes(e, m, now);
         }
+        Ok(())
+    }
 
-        emit!(MilestoneVerified { project_id, id: milestone_id, when });
+    /// Both parties set the rejection weight that blocks verification (0 = rejections never block).
+    pub fn set_veto_weight(ctx: Context<BuyerAndSeller>, veto_weight: u32) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(veto_weight <= total_oracle_weight(e), EscrowError::BadVetoWeight);
+        e.veto_weight = veto_weight;
+        emit!(VetoWeightSet { project_id: e.project_id, veto_weight });
         Ok(())
     }
 
-    /// Releases funds for a verified milestone. Applies fees, insurance, and late penalty if past deliver_by_ts.
-    pub fn release_for_milestone(ctx: Context<ReleaseCommon>, milestone_id: u8) -> Result<()> {
+    /// Releases funds for a verified milestone. Applies fees, insurance, and the milestone's
+    /// late penalty if past its `due_ts` (or the escrow-wide deliver_by_ts when it has none).
+    pub fn release_for_milestone(ctx: Context<ReleaseMilestone>, milestone_id: u16) -> Result<()> {
         let e = &mut ctx.accounts.escrow;
-        require!((milestone_id as usize) < e.milestones_len as usize, EscrowError::BadMilestoneId);
+        let m = &mut ctx.accounts.milestone;
+        require!(
+            e.state == EscrowState::Verified as u8 || e.state == EscrowState::PartiallyReleased as u8,
+            EscrowError::BadState
+        );
+        // A whole-escrow dispute freezes every milestone; a scoped one only its own
+        require!(!e.dispute_open || e.dispute_milestone_scoped, EscrowError::DisputeAlreadyOpen);
+        require!(m.verified && !m.released, EscrowError::MilestoneNotReleasable);
+        require!(!m.disputed, EscrowError::MilestoneDisputed);
 
-        // Pull milestone data in a separate scope to avoid borrow conflicts
-        let payout: u64 = {
-            let m = &e.milestones[milestone_id as usize];
-            require!(m.verified && !m.released, EscrowError::MilestoneNotReleasable);
-            m.amount
+        let now = Clock::get()?.unix_timestamp;
+        let payout = m.payout(e);
+        let penalty_bps = if m.due_ts > 0 {
+            late_penalty_bps(m.penalty_kind, m.penalty_bps, m.penalty_cap_bps, m.due_ts, now)
+        } else {
+            escrow_late_penalty_bps(e, now)
         };
 
         // Guard
         enter_transfer(e)?;
 
-        // Check vault balance
-        require!(ctx.accounts.vault_ata.amount >= payout, EscrowError::VaultBalanceLow);
+        // Check vault balance (excluding retention and any disputed milestone amount)
+        require!(
+            ctx.accounts.vault_ata.amount.saturating_sub(e.retention_held).saturating_sub(e.dispute_frozen) >= payout,
+            EscrowError::VaultBalanceLow
+        );
 
-        let now = Clock::get()?.unix_timestamp;
+        // Retention: withhold a share of every milestone payout; it stays in the vault
+        let retention_cut = calc_retention(payout, e.retention_bps);
+        let paid_out = payout.saturating_sub(retention_cut);
 
         // Fees
-        let (fee_cut, insurance_cut) = calc_fee_splits(payout, e.fee_bps, e.insurance_bps);
-        let mut seller_amount = payout.saturating_sub(fee_cut + insurance_cut);
+        let (fee_cut, insurance_cut) = calc_fee_splits(paid_out, e.fee_bps, e.insurance_bps);
+        let mut seller_amount = paid_out.saturating_sub(fee_cut + insurance_cut);
 
         // Late penalty: reduce seller payout; send to buyer
-        if e.deliver_by_ts > 0 && now > e.deliver_by_ts {
-            let penalty = mul_bps(seller_amount, e.late_penalty_bps);
-            seller_amount = seller_amount.saturating_sub(penalty);
-
-            // penalty → buyer
-            if penalty > 0 {
-                transfer_from_vault(
-                    e,
-                    &ctx.accounts.token_program,
-                    &ctx.accounts.vault_authority,
-                    &ctx.accounts.vault_ata,
-                    &ctx.accounts.buyer_ata,
-                    penalty,
-                )?;
-            }
+        let penalty = mul_bps(seller_amount, penalty_bps);
+        seller_amount = seller_amount.saturating_sub(penalty);
+        if penalty > 0 {
+            transfer_from_vault(
+                e,
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault_authority,
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.buyer_ata,
+                penalty,
+            )?;
         }
 
         // Route fees
@@ -392,10 +1019,8 @@ pub mod construction_escrow {
         }
 
         // Mark milestone as released
-        {
-            let m = &mut e.milestones[milestone_id as usize];
-            m.released = true;
-        }
+        mark_milestone_released(e, m, payout);
+        e.retention_held = e.retention_held.saturating_add(retention_cut);
 
         e.state = EscrowState::PartiallyReleased as u8;
         e.released_ts = now;
@@ -406,8 +1031,10 @@ pub mod construction_escrow {
             project_id: e.project_id,
             id: milestone_id,
             gross: payout,
+            retention_cut,
             fee_cut,
             insurance_cut,
+            penalty,
             seller_received: seller_amount,
         });
         Ok(())
@@ -416,40 +1043,59 @@ pub mod construction_escrow {
     /* ----------------------------- Full Release ---------------------------- */
 
     /// Releases remaining balance to seller after overall verification (and optionally milestones).
+    /// When milestones already paid everything, it only closes the escrow as released and
+    /// starts the warranty clock if delivery was never verified overall.
     pub fn release_payment(ctx: Context<ReleaseCommon>) -> Result<()> {
         let e = &mut ctx.accounts.escrow;
         require!(e.state == EscrowState::Verified as u8 || e.state == EscrowState::PartiallyReleased as u8, EscrowError::BadState);
-
-        // remaining = vault - retention (if retention not released yet)
-        let mut remaining = ctx.accounts.vault_ata.amount;
-        let retention_due = calc_retention(e.amount, e.retention_bps);
-        if !e.retention_released {
-            remaining = remaining.saturating_sub(retention_due.min(remaining));
+        require!(!e.dispute_open || e.dispute_milestone_scoped, EscrowError::DisputeAlreadyOpen);
+
+        // remaining = vault - retention already held back - disputed milestone amount
+        let remaining = ctx.accounts.vault_ata.amount.saturating_sub(e.retention_held).saturating_sub(e.dispute_frozen);
+        if remaining == 0 {
+            // Nothing left to pay, but a scoped dispute still holds funds for the seller
+            require!(!e.dispute_open, EscrowError::NothingToRelease);
+            let now = Clock::get()?.unix_timestamp;
+            e.state = EscrowState::Released as u8;
+            e.released_ts = now;
+            start_warranty(e, now);
+            emit!(PaymentReleased {
+                project_id: e.project_id,
+                seller: e.seller,
+                amount: 0,
+                retention_cut: 0,
+                fee_cut: 0,
+                insurance_cut: 0,
+                penalty: 0,
+                seller_received: 0,
+                when: now
+            });
+            return Ok(());
         }
 
-        require!(remaining > 0, EscrowError::NothingToRelease);
-
         // Guard
         enter_transfer(e)?;
 
-        let (fee_cut, insurance_cut) = calc_fee_splits(remaining, e.fee_bps, e.insurance_bps);
-        let mut seller_amount = remaining.saturating_sub(fee_cut + insurance_cut);
+        // Withhold retention on the final draw too
+        let retention_cut = calc_retention(remaining, e.retention_bps);
+        let paid_out = remaining.saturating_sub(retention_cut);
+
+        let (fee_cut, insurance_cut) = calc_fee_splits(paid_out, e.fee_bps, e.insurance_bps);
+        let mut seller_amount = paid_out.saturating_sub(fee_cut + insurance_cut);
 
-        // Late penalty
+        // Late penalty (liquidated damages accrued since deliver_by_ts + grace)
         let now = Clock::get()?.unix_timestamp;
-        if e.deliver_by_ts > 0 && now > e.deliver_by_ts {
-            let penalty = mul_bps(seller_amount, e.late_penalty_bps);
-            seller_amount = seller_amount.saturating_sub(penalty);
-            if penalty > 0 {
-                transfer_from_vault(
-                    e,
-                    &ctx.accounts.token_program,
-                    &ctx.accounts.vault_authority,
-                    &ctx.accounts.vault_ata,
-                    &ctx.accounts.buyer_ata,
-                    penalty,
-                )?;
-            }
+        let penalty = mul_bps(seller_amount, escrow_late_penalty_bps(e, now));
+        seller_amount = seller_amount.saturating_sub(penalty);
+        if penalty > 0 {
+            transfer_from_vault(
+                e,
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault_authority,
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.buyer_ata,
+                penalty,
+            )?;
         }
 
         // Route fees
@@ -486,8 +1132,10 @@ pub mod construction_escrow {
             )?;
         }
 
+        e.retention_held = e.retention_held.saturating_add(retention_cut);
         e.state = EscrowState::Released as u8;
         e.released_ts = now;
+        start_warranty(e, now);
 
         exit_transfer(e);
 
@@ -495,8 +1143,10 @@ pub mod construction_escrow {
             project_id: e.project_id,
             seller: e.seller,
             amount: remaining,
+            retention_cut,
             fee_cut,
             insurance_cut,
+            penalty,
             seller_received: seller_amount,
             when: e.released_ts
         });
@@ -504,14 +1154,71 @@ pub mod construction_escrow {
         Ok(())
     }
 
-    /// Releases retention after the warranty window passes.
+    /// Both parties agree a retention release schedule: up to MAX_RETENTION_TRANCHES
+    /// ascending timestamps, each releasing `bps` of total retention (must sum to 10,000).
+    /// Without a schedule, all retention matures at `warranty_end_ts`.
+    pub fn set_retention_schedule(ctx: Context<BuyerAndSeller>, release_ts: Vec<i64>, bps: Vec<u16>) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(
+            e.state == EscrowState::Open as u8
+                || e.state == EscrowState::Verified as u8
+                || e.state == EscrowState::PartiallyReleased as u8
+                || e.state == EscrowState::Released as u8,
+            EscrowError::BadState
+        );
+        require!(e.retention_paid == 0 && !e.retention_released, EscrowError::RetentionAlreadyReleased);
+        require!(
+            !release_ts.is_empty() && release_ts.len() <= MAX_RETENTION_TRANCHES && release_ts.len() == bps.len(),
+            EscrowError::BadRetentionSchedule
+        );
+        require!(release_ts.windows(2).all(|w| w[0] < w[1]), EscrowError::BadRetentionSchedule);
+        let total_bps: u32 = bps.iter().map(|b| *b as u32).sum();
+        require!(total_bps == 10_000, EscrowError::BadRetentionSchedule);
+
+        e.retention_tranche_ts = [0i64; MAX_RETENTION_TRANCHES];
+        e.retention_tranche_bps = [0u16; MAX_RETENTION_TRANCHES];
+        for i in 0..release_ts.len() {
+            e.retention_tranche_ts[i] = release_ts[i];
+            e.retention_tranche_bps[i] = bps[i];
+        }
+        e.retention_tranches_len = release_ts.len() as u8;
+        e.retention_tranches_released = 0;
+
+        emit!(RetentionScheduleSet { project_id: e.project_id, release_ts, bps });
+        Ok(())
+    }
+
+    /// Releases every matured retention tranche (minus what was already paid) from the
+    /// unclaimed part of `retention_held`. Tranches are marked released once their share is
+    /// paid and a call with no unpaid matured tranche fails; once every tranche has matured,
+    /// whatever is still held can be swept. Retention is fully released once all tranches
+    /// have matured after final release.
     pub fn release_retention(ctx: Context<ReleaseCommon>) -> Result<()> {
         let e = &mut ctx.accounts.escrow;
         require!(!e.retention_released, EscrowError::RetentionAlreadyReleased);
+        require!(!e.dispute_open, EscrowError::DisputeAlreadyOpen);
+        require!(
+            e.state == EscrowState::Verified as u8
+                || e.state == EscrowState::PartiallyReleased as u8
+                || e.state == EscrowState::Released as u8,
+            EscrowError::BadState
+        );
         let now = Clock::get()?.unix_timestamp;
-        require!(now >= e.warranty_end_ts, EscrowError::WarrantyNotEnded);
 
-        let retention = calc_retention(e.amount, e.retention_bps);
+        let (matured_bps, matured_mask) = matured_retention(e, now);
+        require!(matured_bps > 0, EscrowError::WarrantyNotEnded);
+        require!(
+            matured_mask & !e.retention_tranches_released != 0 || matured_bps >= 10_000,
+            EscrowError::RetentionAlreadyReleased
+        );
+
+        // Tranches are shares of all retention ever withheld (held + already paid)
+        let basis = e.retention_held.saturating_add(e.retention_paid);
+        let target = if matured_bps >= 10_000 { basis } else { mul_bps(basis, matured_bps) };
+        // Retention frozen by an open warranty claim stays in the vault
+        let unclaimed = e.retention_held.saturating_sub(e.retention_frozen);
+        let retention = target.saturating_sub(e.retention_paid).min(unclaimed);
+        require!(retention > 0, EscrowError::NothingToRelease);
         require!(ctx.accounts.vault_ata.amount >= retention, EscrowError::VaultBalanceLow);
 
         // Guard
@@ -553,7 +1260,11 @@ pub mod construction_escrow {
             )?;
         }
 
-        e.retention_released = true;
+        e.retention_held = e.retention_held.saturating_sub(retention);
+        e.retention_paid = e.retention_paid.saturating_add(retention);
+        e.retention_tranches_released = paid_retention_tranches(e, matured_mask);
+        e.retention_released =
+            matured_bps >= 10_000 && e.state == EscrowState::Released as u8 && e.retention_held == 0;
 
         exit_transfer(e);
 
@@ -562,70 +1273,534 @@ pub mod construction_escrow {
             gross: retention,
             fee_cut,
             insurance_cut,
-            seller_received: seller_amount
+            seller_received: seller_amount,
+            tranches_released: e.retention_tranches_released,
+            fully_released: e.retention_released
         });
         Ok(())
     }
 
-    /* ------------------------- Cancel / Dispute Flow ------------------------ */
+    /* ---------------------------- Warranty Claims --------------------------- */
 
-    pub fn request_cancel(ctx: Context<BuyerOrSeller>) -> Result<()> {
+    /// Buyer claims against held retention during the warranty window. The claimed
+    /// amount is frozen and excluded from `release_retention` until settled.
+    pub fn file_warranty_claim(ctx: Context<BuyerOnly>, amount: u64, evidence_hash: [u8; 32]) -> Result<()> {
         let e = &mut ctx.accounts.escrow;
-        require!(e.cancel_requested_by == Pubkey::default(), EscrowError::CancelAlreadyRequested);
-        let caller = ctx.accounts.actor.key();
-        require!(caller == e.buyer || caller == e.seller, EscrowError::Unauthorized);
+        require!(!e.dispute_open, EscrowError::DisputeAlreadyOpen);
+        require!(e.warranty_claim_status == WarrantyClaimStatus::None as u8, EscrowError::ClaimAlreadyOpen);
+        require!(e.warranty_end_ts > 0, EscrowError::WarrantyNotStarted);
+        require!(Clock::get()?.unix_timestamp < e.warranty_end_ts, EscrowError::WarrantyEnded);
+        require!(amount > 0, EscrowError::ZeroAmount);
+        require!(amount <= e.retention_held.saturating_sub(e.retention_frozen), EscrowError::ClaimOverRetention);
 
-        e.cancel_requested_by = caller;
-        emit!(CancelRequested { project_id: e.project_id, by: caller });
+        e.retention_frozen = e.retention_frozen.saturating_add(amount);
+        e.warranty_claim_status = WarrantyClaimStatus::Open as u8;
+        e.warranty_claim_amount = amount;
+        e.warranty_claim_hash = evidence_hash;
+        e.warranty_claims_count = e.warranty_claims_count.saturating_add(1);
+
+        emit!(WarrantyClaimFiled {
+            project_id: e.project_id,
+            claim_no: e.warranty_claims_count,
+            amount,
+            evidence_hash
+        });
         Ok(())
     }
 
-    /// Counterparty approves; refunds remaining vault balance to buyer.
-    pub fn approve_cancel(ctx: Context<ApproveCancel>) -> Result<()> {
+    /// Seller accepts the open claim; the claimed retention is paid to the buyer.
+    pub fn accept_warranty_claim(ctx: Context<AcceptWarrantyClaim>) -> Result<()> {
         let e = &mut ctx.accounts.escrow;
-        let caller = ctx.accounts.actor.key();
-        require!(e.cancel_requested_by != Pubkey::default(), EscrowError::CancelNotRequested);
-        require!(caller != e.cancel_requested_by, EscrowError::Unauthorized);
+        require!(e.warranty_claim_status == WarrantyClaimStatus::Open as u8, EscrowError::NoOpenClaim);
 
-        let remaining = ctx.accounts.vault_ata.amount;
-        require!(remaining > 0, EscrowError::NothingToRelease);
+        let amount = e.warranty_claim_amount;
+        require!(ctx.accounts.vault_ata.amount >= amount, EscrowError::VaultBalanceLow);
 
+        enter_transfer(e)?;
         transfer_from_vault(
             e,
             &ctx.accounts.token_program,
             &ctx.accounts.vault_authority,
             &ctx.accounts.vault_ata,
             &ctx.accounts.buyer_ata,
-            remaining,
+            amount,
         )?;
+        settle_warranty_claim(e, amount);
+        exit_transfer(e);
 
-        e.state = EscrowState::Refunded as u8;
-        emit!(CancelApprovedAndRefunded { project_id: e.project_id, amount: remaining });
+        emit!(WarrantyClaimSettled {
+            project_id: e.project_id,
+            claim_no: e.warranty_claims_count,
+            buyer_received: amount,
+            returned_to_retention: 0,
+            by_arbiter: false
+        });
         Ok(())
     }
 
-    pub fn open_dispute(ctx: Context<BuyerOrSeller>, reason_code: u16, evidence_hash: [u8; 32]) -> Result<()> {
+    /// Seller contests the open claim; it escalates to the arbiter.
+    pub fn contest_warranty_claim(ctx: Context<SellerOnly>) -> Result<()> {
         let e = &mut ctx.accounts.escrow;
-        require!(!e.dispute_open, EscrowError::DisputeAlreadyOpen);
-        e.dispute_open = true;
-        e.state = EscrowState::Dispute as u8;
-        emit!(DisputeOpened { project_id: e.project_id, reason_code, evidence_hash });
+        require!(e.warranty_claim_status == WarrantyClaimStatus::Open as u8, EscrowError::NoOpenClaim);
+        e.warranty_claim_status = WarrantyClaimStatus::Contested as u8;
+        emit!(WarrantyClaimContested { project_id: e.project_id, claim_no: e.warranty_claims_count });
         Ok(())
     }
 
-    /// Arbiter resolves dispute with outcome: Refund, Release, or Split (seller_pct bps).
-    pub fn resolve_dispute(
-        ctx: Context<ArbiterResolve>,
+    /// Arbiter rules on a contested claim: `buyer_amount` (≤ claim) goes to the buyer,
+    /// the rest is unfrozen back into retention.
+    pub fn resolve_warranty_claim(ctx: Context<ResolveWarrantyClaim>, buyer_amount: u64) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(e.warranty_claim_status == WarrantyClaimStatus::Contested as u8, EscrowError::NoOpenClaim);
+        let claim = e.warranty_claim_amount;
+        require!(buyer_amount <= claim, EscrowError::ClaimOverRetention);
+        require!(ctx.accounts.vault_ata.amount >= buyer_amount, EscrowError::VaultBalanceLow);
+
+        enter_transfer(e)?;
+        if buyer_amount > 0 {
+            transfer_from_vault(
+                e,
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault_authority,
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.buyer_ata,
+                buyer_amount,
+            )?;
+        }
+        settle_warranty_claim(e, buyer_amount);
+        exit_transfer(e);
+
+        emit!(WarrantyClaimSettled {
+            project_id: e.project_id,
+            claim_no: e.warranty_claims_count,
+            buyer_received: buyer_amount,
+            returned_to_retention: claim - buyer_amount,
+            by_arbiter: true
+        });
+        Ok(())
+    }
+
+    /* ------------------------- Cancel / Dispute Flow ------------------------ */
+
+    pub fn request_cancel(ctx: Context<BuyerOrSeller>) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(e.cancel_requested_by == Pubkey::default(), EscrowError::CancelAlreadyRequested);
+        let caller = ctx.accounts.actor.key();
+
+        e.cancel_requested_by = caller;
+        emit!(CancelRequested { project_id: e.project_id, by: caller });
+        Ok(())
+    }
+
+    /// Counterparty approves; refunds remaining vault balance to buyer. Not while a dispute is
+    /// open or the escrow is escalated to `Dispute`.
+    pub fn approve_cancel(ctx: Context<ApproveCancel>) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        let caller = ctx.accounts.actor.key();
+        require!(e.cancel_requested_by != Pubkey::default(), EscrowError::CancelNotRequested);
+        require!(caller != e.cancel_requested_by, EscrowError::Unauthorized);
+        require!(!e.dispute_open, EscrowError::DisputeAlreadyOpen); // the ruling decides the funds
+        // An oracle escalation is headed for a ruling too: open the case or clear the rejections first
+        require!(e.state != EscrowState::Dispute as u8, EscrowError::BadState);
+
+        let remaining = ctx.accounts.vault_ata.amount;
+        require!(remaining > 0, EscrowError::NothingToRelease);
+
+        transfer_from_vault(
+            e,
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault_authority,
+            &ctx.accounts.vault_ata,
+            &ctx.accounts.buyer_ata,
+            remaining,
+        )?;
+
+        e.retention_held = 0;
+        e.retention_frozen = 0;
+        e.warranty_claim_status = WarrantyClaimStatus::None as u8;
+        e.state = EscrowState::Refunded as u8;
+        emit!(CancelApprovedAndRefunded { project_id: e.project_id, amount: remaining });
+        Ok(())
+    }
+
+    /// Open a dispute record; the counterparty has `Config.dispute_response_secs` (default
+    /// `DISPUTE_RESPONSE_SECS`) to respond before arbiters may rule by default. Passing `milestone` scopes the dispute to that
+    /// milestone: only its payout is frozen and the rest of the escrow carries on.
+    /// The opener deposits `Config.dispute_bond` into a per-dispute bond vault.
+    pub fn open_dispute(ctx: Context<OpenDispute>, reason_code: u16, evidence_hash: [u8; 32]) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(!e.dispute_open, EscrowError::DisputeAlreadyOpen);
+        let now = Clock::get()?.unix_timestamp;
+        let claimant = ctx.accounts.actor.key();
+        let respondent = if claimant == e.buyer { e.seller } else { e.buyer };
+
+        let milestone_id = match ctx.accounts.milestone.as_mut() {
+            Some(m) => {
+                require!(
+                    e.state == EscrowState::Open as u8
+                        || e.state == EscrowState::Verified as u8
+                        || e.state == EscrowState::PartiallyReleased as u8,
+                    EscrowError::BadState
+                );
+                require!(!m.released, EscrowError::MilestoneNotReleasable);
+                let frozen = m.payout(e);
+                require!(
+                    ctx.accounts.vault_ata.amount.saturating_sub(e.retention_held) >= frozen,
+                    EscrowError::VaultBalanceLow
+                );
+                m.disputed = true;
+                e.dispute_milestone_scoped = true;
+                e.dispute_milestone_id = m.id;
+                e.dispute_frozen = frozen;
+                Some(m.id)
+            }
+            None => {
+                // Dispute (without an open case) = escalated by oracle rejections
+                require!(
+                    e.state == EscrowState::Open as u8
+                        || e.state == EscrowState::Verified as u8
+                        || e.state == EscrowState::PartiallyReleased as u8
+                        || e.state == EscrowState::Dispute as u8,
+                    EscrowError::BadState
+                );
+                e.state = EscrowState::Dispute as u8;
+                e.escalated_from_state = 0; // the case replaces any escalation
+                None
+            }
+        };
+
+        e.dispute_open = true;
+        e.disputes_count += 1;
+        clear_dispute_votes(e);
+
+        let d = &mut ctx.accounts.dispute;
+        d.escrow = e.key();
+        d.dispute_no = e.disputes_count;
+        d.claimant = claimant;
+        d.respondent = respondent;
+        d.reason_code = reason_code;
+        d.opened_ts = now;
+        let cfg_secs = ctx.accounts.config.dispute_response_secs;
+        let response_secs = if cfg_secs > 0 { cfg_secs } else { DISPUTE_RESPONSE_SECS };
+        d.response_deadline_ts = now.saturating_add(response_secs);
+        d.responded_ts = 0;
+        d.round = 1;
+        d.last_submitter = claimant;
+        d.claimant_submissions = 1;
+        d.respondent_submissions = 0;
+        d.claimant_evidence_hash = evidence_hash;
+        d.respondent_evidence_hash = [0u8; 32];
+        // The panel that rules is the one in place now; later panel changes don't apply
+        let (panel, threshold) = arbiter_panel(&ctx.accounts.config, e);
+        d.arbiters = [Pubkey::default(); MAX_ARBITERS];
+        d.arbiters[..panel.len()].copy_from_slice(panel);
+        d.arbiters_len = panel.len() as u8;
+        d.arbiter_threshold = threshold;
+        d.ruled_ts = 0;
+        d.ruling_outcome = 0;
+        d.ruling_seller_pct_bps = 0;
+        d.ruled_by = Pubkey::default();
+        d.appeal_deadline_ts = 0;
+        d.resolved_ts = 0;
+        d.outcome = 0;
+        d.by_default = false;
+        d.appellant = Pubkey::default();
+        d.appealed_ts = 0;
+        d.appeal_decide_by_ts = 0;
+        d.appealed_seller_pct_bps = 0;
+        d.appeal_decided = false;
+        d.appeal_bond_amount = 0;
+        d.appeal_bond_bump = 0;
+        d.milestone_scoped = milestone_id.is_some();
+        d.milestone_id = milestone_id.unwrap_or(0);
+        d.frozen_amount = e.dispute_frozen;
+        d.bond_amount = ctx.accounts.config.dispute_bond;
+        d.bond_bump = ctx.bumps.bond_vault;
+        d.bump = ctx.bumps.dispute;
+
+        // Opener → bond vault
+        if d.bond_amount > 0 {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.actor_ata.to_account_info(),
+                to: ctx.accounts.bond_vault.to_account_info(),
+                authority: ctx.accounts.actor.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+            token::transfer(cpi_ctx, d.bond_amount)?;
+        }
+
+        emit!(DisputeOpened {
+            project_id: e.project_id,
+            dispute_no: d.dispute_no,
+            milestone_id,
+            frozen_amount: e.dispute_frozen,
+            bond: d.bond_amount,
+            claimant,
+            respondent,
+            reason_code,
+            evidence_hash,
+            response_deadline_ts: d.response_deadline_ts
+        });
+        Ok(())
+    }
+
+    /// Claimant or respondent adds evidence to the open dispute. The respondent's first
+    /// submission is their response; each change of submitter starts a new round.
+    pub fn submit_dispute_evidence(ctx: Context<SubmitDisputeEvidence>, hash: [u8; 32], uri: Vec<u8>) -> Result<()> {
+        let e = &ctx.accounts.escrow;
+        require!(e.dispute_open, EscrowError::NoOpenDispute);
+        let by = ctx.accounts.actor.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        let d = &mut ctx.accounts.dispute;
+        // The record is what the panel ruled on; later evidence would rewrite it
+        require!(d.ruled_ts == 0, EscrowError::RulingAlreadyPosted);
+        if by != d.last_submitter {
+            d.round = d.round.saturating_add(1);
+            d.last_submitter = by;
+        }
+        if by == d.claimant {
+            d.claimant_submissions = d.claimant_submissions.saturating_add(1);
+            d.claimant_evidence_hash = hash;
+        } else {
+            if d.responded_ts == 0 {
+                d.responded_ts = now;
+            }
+            d.respondent_submissions = d.respondent_submissions.saturating_add(1);
+            d.respondent_evidence_hash = hash;
+        }
+
+        let mut short = [0u8; 96];
+        let n = short.len().min(uri.len());
+        short[..n].copy_from_slice(&uri[..n]);
+        emit!(DisputeEvidenceSubmitted {
+            project_id: e.project_id,
+            dispute_no: d.dispute_no,
+            by,
+            round: d.round,
+            hash,
+            uri_prefix: short,
+            when: now
+        });
+        Ok(())
+    }
+
+    /// Both parties replace the Config arbiter panel for this escrow (empty list = use Config).
+    pub fn set_escrow_arbiters(ctx: Context<BuyerAndSeller>, arbiters: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(!e.dispute_open, EscrowError::DisputeAlreadyOpen);
+        if !arbiters.is_empty() {
+            validate_arbiter_panel(&arbiters, threshold)?;
+        }
+        e.arbiters = [Pubkey::default(); MAX_ARBITERS];
+        for (i, pk) in arbiters.iter().enumerate() {
+            e.arbiters[i] = *pk;
+        }
+        e.arbiters_len = arbiters.len() as u8;
+        e.arbiter_threshold = if arbiters.is_empty() { 0 } else { threshold };
+        emit!(ArbiterPanelSet { project_id: Some(e.project_id), arbiters, threshold: e.arbiter_threshold });
+        Ok(())
+    }
+
+    /// A panel arbiter records a vote: Refund, Release, or Split (seller_pct bps).
+    /// Voting opens once the respondent has answered or the response deadline has passed
+    /// (a default ruling). Once `threshold` votes agree on an outcome the ruling is posted
+    /// and the appeal window starts; a Split uses the median of the agreeing percentages.
+    /// Until then an arbiter may vote again to replace its vote, so a split panel can
+    /// still converge. Funds only move in `execute_ruling`.
+    pub fn cast_dispute_vote(
+        ctx: Context<CastDisputeVote>,
         outcome: DisputeOutcome,
         seller_pct_bps: u16,
     ) -> Result<()> {
         let e = &mut ctx.accounts.escrow;
         require!(e.dispute_open, EscrowError::NoOpenDispute);
+        require!(seller_pct_bps <= 10_000, EscrowError::BadBps);
+
+        // Only the panel snapshotted when the dispute was opened votes on it
+        let arbiter = ctx.accounts.arbiter.key();
+        let d = &mut ctx.accounts.dispute;
+        require!(d.arbiters[..d.arbiters_len as usize].contains(&arbiter), EscrowError::NotArbiter);
+        let threshold = d.arbiter_threshold;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(d.ruled_ts == 0, EscrowError::RulingAlreadyPosted);
+        require!(d.responded_ts > 0 || now > d.response_deadline_ts, EscrowError::AwaitingResponse);
+        let by_default = d.responded_ts == 0;
+
+        // One vote per arbiter per dispute (the vote PDA); voting again replaces it
+        let vote = &mut ctx.accounts.vote;
+        let replaced = vote.arbiter == arbiter;
+        if replaced {
+            remove_dispute_vote(e, vote.outcome, vote.seller_pct_bps);
+        }
+        vote.escrow = e.key();
+        vote.dispute_no = e.disputes_count;
+        vote.arbiter = arbiter;
+        vote.outcome = outcome as u8;
+        vote.seller_pct_bps = if outcome == DisputeOutcome::Split { seller_pct_bps } else { 0 };
+        vote.ts = now;
+        vote.bump = ctx.bumps.vote;
+
+        let agreeing = match outcome {
+            DisputeOutcome::Refund => {
+                e.dispute_refund_votes += 1;
+                e.dispute_refund_votes
+            }
+            DisputeOutcome::Release => {
+                e.dispute_release_votes += 1;
+                e.dispute_release_votes
+            }
+            DisputeOutcome::Split => {
+                let idx = e.dispute_split_votes as usize;
+                require!(idx < MAX_ARBITERS, EscrowError::BadArbiterPanel);
+                e.dispute_split_bps[idx] = seller_pct_bps;
+                e.dispute_split_votes += 1;
+                e.dispute_split_votes
+            }
+        };
+
+        emit!(DisputeVoteCast {
+            project_id: e.project_id,
+            dispute_no: e.disputes_count,
+            arbiter,
+            outcome,
+            seller_pct_bps: vote.seller_pct_bps,
+            votes_for_outcome: agreeing,
+            threshold,
+            replaced
+        });
+
+        if agreeing < threshold {
+            return Ok(());
+        }
+
+        // Threshold reached: post the ruling; it executes after the appeal window
+        let seller_pct_bps = if outcome == DisputeOutcome::Split {
+            median_bps(&e.dispute_split_bps[..e.dispute_split_votes as usize])
+        } else {
+            0
+        };
+        d.ruled_ts = now;
+        d.ruling_outcome = outcome as u8;
+        d.ruling_seller_pct_bps = seller_pct_bps;
+        d.ruled_by = arbiter;
+        d.by_default = by_default;
+        d.appeal_deadline_ts = now.saturating_add(ctx.accounts.config.appeal_window_secs);
+
+        emit!(RulingPosted {
+            project_id: e.project_id,
+            dispute_no: d.dispute_no,
+            outcome,
+            seller_pct_bps,
+            by_default,
+            appeal_deadline_ts: d.appeal_deadline_ts
+        });
+        Ok(())
+    }
+
+    /// Claimant or respondent appeals a posted ruling to `Config.appeal_arbiter` before the
+    /// appeal window closes, depositing `Config.appeal_bond`. One appeal per dispute; the
+    /// appeal arbiter has `APPEAL_DECISION_SECS` to decide it.
+    pub fn appeal_ruling(ctx: Context<AppealRuling>, evidence_hash: [u8; 32]) -> Result<()> {
+        let cfg = &ctx.accounts.config;
+        require!(cfg.appeal_arbiter != Pubkey::default(), EscrowError::AppealsDisabled);
+        let e = &ctx.accounts.escrow;
+        let d = &mut ctx.accounts.dispute;
+        require!(e.dispute_open && d.ruled_ts > 0, EscrowError::NoRuling);
+        require!(d.appellant == Pubkey::default(), EscrowError::AlreadyAppealed);
+        let now = Clock::get()?.unix_timestamp;
+        require!(now < d.appeal_deadline_ts, EscrowError::AppealWindowClosed);
+
+        let by = ctx.accounts.actor.key();
+        d.appellant = by;
+        d.appealed_ts = now;
+        d.appeal_decide_by_ts = now.saturating_add(APPEAL_DECISION_SECS);
+        d.appealed_seller_pct_bps = seller_share_bps(d.ruling_outcome, d.ruling_seller_pct_bps);
+        d.appeal_bond_amount = cfg.appeal_bond;
+        d.appeal_bond_bump = ctx.bumps.appeal_bond_vault;
+
+        // Appellant → appeal bond vault
+        if d.appeal_bond_amount > 0 {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.actor_ata.to_account_info(),
+                to: ctx.accounts.appeal_bond_vault.to_account_info(),
+                authority: ctx.accounts.actor.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+            token::transfer(cpi_ctx, d.appeal_bond_amount)?;
+        }
+
+        emit!(RulingAppealed {
+            project_id: e.project_id,
+            dispute_no: d.dispute_no,
+            by,
+            bond: d.appeal_bond_amount,
+            evidence_hash,
+            decide_by_ts: d.appeal_decide_by_ts
+        });
+        Ok(())
+    }
+
+    /// The appeal arbiter replaces the appealed ruling by `appeal_decide_by_ts`; the decision
+    /// is final and the arbiter fee goes to the appeal arbiter.
+    pub fn decide_appeal(ctx: Context<DecideAppeal>, outcome: DisputeOutcome, seller_pct_bps: u16) -> Result<()> {
+        require!(seller_pct_bps <= 10_000, EscrowError::BadBps);
+        let e = &ctx.accounts.escrow;
+        let d = &mut ctx.accounts.dispute;
+        require!(e.dispute_open, EscrowError::NoOpenDispute);
+        require!(d.appellant != Pubkey::default() && !d.appeal_decided, EscrowError::NoPendingAppeal);
+        require!(Clock::get()?.unix_timestamp <= d.appeal_decide_by_ts, EscrowError::AppealLapsed);
+
+        d.ruling_outcome = outcome as u8;
+        d.ruling_seller_pct_bps = if outcome == DisputeOutcome::Split { seller_pct_bps } else { 0 };
+        d.ruled_by = ctx.accounts.appeal_arbiter.key();
+        d.appeal_decided = true;
+
+        emit!(AppealDecided {
+            project_id: e.project_id,
+            dispute_no: d.dispute_no,
+            outcome,
+            seller_pct_bps: d.ruling_seller_pct_bps
+        });
+        Ok(())
+    }
+
+    /// Permissionless: pay out the posted ruling once the appeal window has passed with no
+    /// appeal, or once the appeal has been decided. An appeal still undecided after
+    /// `appeal_decide_by_ts` lapses: the original ruling stands and the appeal bond is
+    /// returned. Settles the dispute and appeal bonds.
+    pub fn execute_ruling(ctx: Context<ExecuteRuling>) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        let d = &mut ctx.accounts.dispute;
+        require!(e.dispute_open && d.ruled_ts > 0, EscrowError::NoRuling);
+        let now = Clock::get()?.unix_timestamp;
+        let appealed = d.appellant != Pubkey::default();
+        let appeal_lapsed = appealed && !d.appeal_decided;
+        if appealed {
+            require!(d.appeal_decided || now > d.appeal_decide_by_ts, EscrowError::AppealPending);
+            require!(
+                ctx.accounts.appeal_bond_vault.is_some() && ctx.accounts.appellant.is_some(),
+                EscrowError::NoPendingAppeal
+            );
+        } else {
+            require!(now >= d.appeal_deadline_ts, EscrowError::AppealWindowOpen);
+        }
+
+        // Milestone disputes must pass the disputed milestone (seeds checked in the context)
+        if e.dispute_milestone_scoped {
+            require!(ctx.accounts.milestone.is_some(), EscrowError::BadMilestoneId);
+        }
+
+        let outcome = dispute_outcome_from_u8(d.ruling_outcome);
+        let seller_pct_bps = d.ruling_seller_pct_bps;
+        let by_default = d.by_default;
 
-        // Guard
         enter_transfer(e)?;
 
-        let total = ctx.accounts.vault_ata.amount;
+        // A milestone dispute splits only the frozen milestone payout; otherwise the whole vault
+        let scoped = e.dispute_milestone_scoped;
+        let total = if scoped { e.dispute_frozen } else { ctx.accounts.vault_ata.amount };
         require!(total > 0, EscrowError::NothingToRelease);
 
         let (buyer_amt, seller_amt) = match outcome {
@@ -637,22 +1812,42 @@ pub mod construction_escrow {
             }
         };
 
+        // Arbiter fee on the disputed amount, borne per `Config.arbiter_fee_payer`
+        let (buyer_fee, seller_fee) = arbiter_fee_shares(&ctx.accounts.config, total, buyer_amt, seller_amt);
+        let arbiter_fee = buyer_fee + seller_fee;
+        let buyer_net = buyer_amt - buyer_fee;
+
+        // Milestone rulings still withhold retention from the seller portion
+        let seller_gross = seller_amt - seller_fee;
+        let retention_cut = if scoped { calc_retention(seller_gross, e.retention_bps) } else { 0 };
+        let paid_out = seller_gross.saturating_sub(retention_cut);
+
         // Apply fees on the seller portion only (platform earns on payout)
-        let (fee_cut, insurance_cut) = if seller_amt > 0 {
-            calc_fee_splits(seller_amt, e.fee_bps, e.insurance_bps)
+        let (fee_cut, insurance_cut) = if paid_out > 0 {
+            calc_fee_splits(paid_out, e.fee_bps, e.insurance_bps)
         } else {
             (0, 0)
         };
-        let seller_net = seller_amt.saturating_sub(fee_cut + insurance_cut);
+        let seller_net = paid_out.saturating_sub(fee_cut + insurance_cut);
 
-        if buyer_amt > 0 {
+        if buyer_net > 0 {
             transfer_from_vault(
                 e,
                 &ctx.accounts.token_program,
                 &ctx.accounts.vault_authority,
                 &ctx.accounts.vault_ata,
                 &ctx.accounts.buyer_ata,
-                buyer_amt,
+                buyer_net,
+            )?;
+        }
+        if arbiter_fee > 0 {
+            transfer_from_vault(
+                e,
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault_authority,
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.arbiter_ata,
+                arbiter_fee,
             )?;
         }
         if seller_net > 0 {
@@ -686,19 +1881,116 @@ pub mod construction_escrow {
             )?;
         }
 
+        // Dispute bond: back to a claimant awarded at least half, otherwise forfeited
+        let bond = d.bond_amount;
+        let claimant_is_buyer = d.claimant == e.buyer;
+        let claimant_share = if claimant_is_buyer { buyer_amt } else { seller_amt };
+        let bond_refunded = claimant_share.saturating_mul(2) >= total;
+        if bond > 0 {
+            let to_treasury = ctx.accounts.config.dispute_bond_forfeit == BondForfeit::Treasury as u8;
+            let bond_to = if !bond_refunded && to_treasury {
+                &ctx.accounts.treasury_ata
+            } else if bond_refunded == claimant_is_buyer {
+                &ctx.accounts.buyer_ata // buyer won their own claim, or the seller lost theirs
+            } else {
+                &ctx.accounts.seller_ata
+            };
+            transfer_from_vault(
+                e,
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault_authority,
+                &ctx.accounts.bond_vault,
+                bond_to,
+                bond,
+            )?;
+        }
+        close_vault_token_account(
+            e,
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault_authority,
+            &ctx.accounts.bond_vault,
+            &ctx.accounts.claimant,
+        )?;
+
+        // Appeal bond: back to an appellant whose share improved on appeal (or whose appeal
+        // lapsed undecided), otherwise forfeited
+        let appeal_bond = d.appeal_bond_amount;
+        let mut appeal_bond_refunded = false;
+        if let (Some(appeal_vault), Some(appellant)) = (&ctx.accounts.appeal_bond_vault, &ctx.accounts.appellant) {
+            let appellant_is_buyer = d.appellant == e.buyer;
+            let final_share = seller_share_bps(d.ruling_outcome, d.ruling_seller_pct_bps);
+            appeal_bond_refunded = if appeal_lapsed {
+                true
+            } else if appellant_is_buyer {
+                final_share < d.appealed_seller_pct_bps
+            } else {
+                final_share > d.appealed_seller_pct_bps
+            };
+            if appeal_bond > 0 {
+                let to_treasury = ctx.accounts.config.dispute_bond_forfeit == BondForfeit::Treasury as u8;
+                let bond_to = if !appeal_bond_refunded && to_treasury {
+                    &ctx.accounts.treasury_ata
+                } else if appeal_bond_refunded == appellant_is_buyer {
+                    &ctx.accounts.buyer_ata
+                } else {
+                    &ctx.accounts.seller_ata
+                };
+                transfer_from_vault(
+                    e,
+                    &ctx.accounts.token_program,
+                    &ctx.accounts.vault_authority,
+                    appeal_vault,
+                    bond_to,
+                    appeal_bond,
+                )?;
+            }
+            close_vault_token_account(e, &ctx.accounts.token_program, &ctx.accounts.vault_authority, appeal_vault, appellant)?;
+        }
+
+        let milestone_id = if scoped {
+            // Milestone settled by the ruling; the rest of the escrow is untouched
+            let m = ctx.accounts.milestone.as_mut().ok_or(EscrowError::BadMilestoneId)?;
+            m.disputed = false;
+            let frozen = e.dispute_frozen;
+            mark_milestone_released(e, m, frozen);
+            e.retention_held = e.retention_held.saturating_add(retention_cut);
+            e.dispute_milestone_scoped = false;
+            e.dispute_frozen = 0;
+            Some(m.id)
+        } else {
+            e.retention_held = 0;
+            e.retention_frozen = 0;
+            e.warranty_claim_status = WarrantyClaimStatus::None as u8;
+            e.state = if seller_amt > 0 { EscrowState::Released as u8 } else { EscrowState::Refunded as u8 };
+            None
+        };
         e.dispute_open = false;
-        e.state = if seller_amt > 0 { EscrowState::Released as u8 } else { EscrowState::Refunded as u8 };
-        e.released_ts = Clock::get()?.unix_timestamp;
+        e.released_ts = now;
 
         exit_transfer(e);
 
+        d.resolved_ts = now;
+        d.outcome = outcome as u8;
+
         emit!(DisputeResolved {
             project_id: e.project_id,
+            dispute_no: d.dispute_no,
+            milestone_id,
             outcome,
-            buyer_received: buyer_amt,
+            seller_pct_bps,
+            by_default,
+            bond,
+            bond_refunded,
+            appealed,
+            appeal_lapsed,
+            appeal_bond,
+            appeal_bond_refunded,
+            retention_cut,
+            buyer_received: buyer_net,
             seller_received: seller_net,
             fee_cut,
-            insurance_cut
+            insurance_cut,
+            arbiter_fee
         });
         Ok(())
     }
@@ -826,17 +2118,78 @@ pub mod construction_escrow {
 
     /* -------------------------- Authority Management ------------------------ */
 
-    pub fn update_oracles(ctx: Context<BuyerOrSeller>, new_oracles: Vec<Pubkey>, new_quorum_m: u8) -> Result<()> {
-        require!(new_oracles.len() <= MAX_ORACLES, EscrowError::TooManyOracles);
-        require!(new_quorum_m >= QUORUM_MIN, EscrowError::BadQuorum);
+    /// Propose a new weighted oracle set/threshold; it only takes effect once the counterparty accepts.
+    pub fn propose_oracles(
+        ctx: Context<BuyerOrSeller>,
+        new_oracles: Vec<Pubkey>,
+        new_weights: Vec<u16>,
+        new_quorum_weight: u32,
+    ) -> Result<()> {
+        validate_oracle_set(&new_oracles, &new_weights, new_quorum_weight)?;
         let e = &mut ctx.accounts.escrow;
-        e.oracles = [Pubkey::default(); MAX_ORACLES];
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            e.oracles_proposed_by == Pubkey::default() || now > e.oracles_proposal_expires_ts,
+            EscrowError::ProposalPending
+        );
+
+        e.pending_oracles = [Pubkey::default(); MAX_ORACLES];
+        e.pending_oracle_weights = [0u16; MAX_ORACLES];
         for (i, pk) in new_oracles.iter().enumerate() {
-            e.oracles[i] = *pk;
+            e.pending_oracles[i] = *pk;
+            e.pending_oracle_weights[i] = new_weights[i];
         }
-        e.oracles_len = new_oracles.len() as u8;
-        e.quorum_m = new_quorum_m;
-        emit!(OraclesUpdated { project_id: e.project_id, quorum_m: new_quorum_m, count: e.oracles_len });
+        e.pending_oracles_len = new_oracles.len() as u8;
+        e.pending_quorum_weight = new_quorum_weight;
+        e.oracles_proposed_by = ctx.accounts.actor.key();
+        e.oracles_proposal_expires_ts = proposal_expiry(e, now);
+
+        emit!(OraclesProposed {
+            project_id: e.project_id,
+            by: e.oracles_proposed_by,
+            oracles: new_oracles,
+            weights: new_weights,
+            quorum_weight: new_quorum_weight,
+            expires_ts: e.oracles_proposal_expires_ts
+        });
+        Ok(())
+    }
+
+    /// Counterparty countersigns the pending oracle set.
+    pub fn accept_oracles(ctx: Context<BuyerOrSeller>) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        let caller = ctx.accounts.actor.key();
+        require!(e.oracles_proposed_by != Pubkey::default(), EscrowError::NoPendingProposal);
+        require!(caller != e.oracles_proposed_by, EscrowError::Unauthorized);
+        require!(Clock::get()?.unix_timestamp <= e.oracles_proposal_expires_ts, EscrowError::ProposalExpired);
+
+        e.oracles = e.pending_oracles;
+        e.oracles_len = e.pending_oracles_len;
+        e.oracle_weights = e.pending_oracle_weights;
+        e.quorum_weight = e.pending_quorum_weight;
+        clear_oracles_proposal(e);
+
+        // Oracle indices changed: drop delivery votes; milestone votes reset lazily
+        e.delivery_votes = 0;
+        e.delivery_vote_expiry = [0i64; MAX_ORACLES];
+        e.delivery_rejections = 0;
+        restore_after_escalation(e);
+        e.oracle_set_version = e.oracle_set_version.wrapping_add(1);
+        // Indices moved too: start every nonce past all old ones so no earlier attestation replays
+        let next_nonce = e.oracle_nonces.iter().max().copied().unwrap_or(0).wrapping_add(1);
+        e.oracle_nonces = [next_nonce; MAX_ORACLES];
+
+        emit!(ProposalAccepted { project_id: e.project_id, kind: ChangeKind::Oracles, by: caller });
+        emit!(OraclesUpdated { project_id: e.project_id, quorum_weight: e.quorum_weight, count: e.oracles_len });
+        Ok(())
+    }
+
+    /// Counterparty rejects (or proposer withdraws) the pending oracle set.
+    pub fn reject_oracles(ctx: Context<BuyerOrSeller>) -> Result<()> {
+        let e = &mut ctx.accounts.escrow;
+        require!(e.oracles_proposed_by != Pubkey::default(), EscrowError::NoPendingProposal);
+        clear_oracles_proposal(e);
+        emit!(ProposalRejected { project_id: e.project_id, kind: ChangeKind::Oracles, by: ctx.accounts.actor.key() });
         Ok(())
     }
 
@@ -849,9 +2202,27 @@ pub mod construction_escrow {
 
     /* -------------------------- Cron-friendly Timeout ---------------------- */
 
-    /// Iterate over timeouts (stubbed for PoC; batching left for future).
-    pub fn process_timeouts(_ctx: Context<ProcessTimeouts>, _limit: u8) -> Result<()> {
-        emit!(TimeoutsProcessed { processed: 0 });
+    /// Keeper sweep: walks remaining_accounts in groups of
+    /// [escrow, vault_authority, vault_ata, buyer_ata] and applies `expire_and_refund`
+    /// to each expired `Open` escrow, up to `limit`. Ineligible groups are skipped.
+    pub fn process_timeouts<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ProcessTimeouts<'info>>,
+        limit: u8,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let mut project_ids: Vec<u64> = Vec::new();
+
+        for group in ctx.remaining_accounts.chunks_exact(TIMEOUT_ACCOUNTS_PER_ESCROW) {
+            if project_ids.len() >= limit as usize {
+                break;
+            }
+            if let Some((project_id, amount)) = try_expire_escrow(&ctx.accounts.token_program, group, now)? {
+                emit!(ExpiredAndRefunded { project_id, amount });
+                project_ids.push(project_id);
+            }
+        }
+
+        emit!(TimeoutsProcessed { processed: project_ids.len() as u8, project_ids });
         Ok(())
     }
 }
@@ -867,14 +2238,33 @@ pub struct Config {
     pub fee_bps: u16,
     pub insurance_bps: u16,
     pub retention_bps: u16,
-    pub warranty_days: i64,
+    pub warranty_days: i64, // default suggested to clients
+    pub min_warranty_days: i64,
+    pub max_warranty_days: i64,
     pub quorum_m: u8,
-    pub arbiter: Pubkey,
+    pub arbiter: Pubkey, // resolves warranty claims
+    // Default dispute panel: `arbiter_threshold` matching votes resolve a dispute
+    pub arbiters: [Pubkey; MAX_ARBITERS],
+    pub arbiters_len: u8,
+    pub arbiter_threshold: u8,
+    // Bond to open a dispute (escrow mint units) and where a losing claimant's bond goes
+    pub dispute_bond: u64,
+    pub dispute_bond_forfeit: u8, // BondForfeit as u8
+    // Arbiter fee (bps of the disputed amount) paid to `DisputeRecord.ruled_by`, and who bears it
+    pub arbiter_fee_bps: u16,
+    pub arbiter_fee_payer: u8, // ArbiterFeePayer as u8
+    // Appeals: window after a ruling, secondary arbiter (default = disabled), appellant bond
+    pub appeal_window_secs: i64,
+    pub appeal_arbiter: Pubkey,
+    pub appeal_bond: u64,
     pub bump: u8,
-    pub reserved: [u8; 64],
+    // Respondent's window to answer a dispute (0 = DISPUTE_RESPONSE_SECS); taken from `reserved`
+    pub dispute_response_secs: i64,
+    pub reserved: [u8; 56],
 }
 impl Config {
-    pub const SPACE: usize = 8 + 32 + 32 + 32 + 32 + 2 + 2 + 2 + 8 + 1 + 32 + 1 + 64;
+    pub const SPACE: usize = 8 + 32 + 32 + 32 + 32 + 2 + 2 + 2 + 8 + 8 + 8 + 1 + 32
+        + (32 * MAX_ARBITERS) + 1 + 1 + 8 + 1 + 2 + 1 + 8 + 32 + 8 + 1 + 8 + 56;
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
@@ -894,6 +2284,46 @@ pub enum DisputeOutcome {
     Split,
 }
 
+/// Recipient of a dispute bond when the claimant loses.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BondForfeit {
+    Counterparty = 0,
+    Treasury = 1,
+}
+
+/// Who bears the arbiter fee: the party awarded less (halved on a tie), both halves, or a fixed party.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ArbiterFeePayer {
+    Loser = 0,
+    Split = 1,
+    Buyer = 2,
+    Seller = 3,
+}
+
+/// Late penalty schedule for a milestone: none, flat bps once late, or bps per started
+/// day late capped at `penalty_cap_bps`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PenaltyKind {
+    None = 0,
+    Flat = 1,
+    PerDay = 2,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum WarrantyClaimStatus {
+    None = 0,
+    Open = 1,
+    Contested = 2,
+}
+
+/// Which two-party change a proposal event refers to.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Oracles,
+    Deadlines,
+    ChangeOrder,
+}
+
 #[account]
 pub struct Escrow {
     // Keys
@@ -908,13 +2338,26 @@ pub struct Escrow {
     pub fee_bps: u16,
     pub insurance_bps: u16,
     pub retention_bps: u16,
-    pub late_penalty_bps: u16, // default 0 unless set
+    pub late_penalty_bps: u16, // per day late; default 0 unless set
+    pub late_penalty_cap_bps: u16,
+    pub late_grace_secs: i64,
     pub price_snapshot_1e6: u64, // optional USD notional snapshot
 
-    // Oracles & quorum
-    pub quorum_m: u8,
+    // Oracles & quorum (weighted: verified once live vote weight >= quorum_weight)
+    pub quorum_weight: u32,
     pub oracles_len: u8,
     pub oracles: [Pubkey; MAX_ORACLES],
+    pub oracle_weights: [u16; MAX_ORACLES], // parallel to `oracles`
+    // Delivery votes persisted across transactions (bit i = oracles[i]) with per-vote expiry
+    pub delivery_votes: u8,
+    pub delivery_vote_expiry: [i64; MAX_ORACLES],
+    pub delivery_vote_hash: [u8; 32], // evidence the live votes refer to
+    pub oracle_set_version: u16, // bumped when the oracle set changes
+    pub oracle_nonces: [u32; MAX_ORACLES], // attestations must carry oracles[i]'s current nonce
+    // Oracle rejections (bit i = oracles[i]); verification is blocked while their weight >= veto_weight
+    pub delivery_rejections: u8,
+    pub veto_weight: u32, // 0 = rejections never block
+    pub escalated_from_state: u8, // state before delivery rejections escalated to Dispute (0 = none)
 
     // Lifecycle
     pub state: u8,
@@ -923,11 +2366,38 @@ pub struct Escrow {
     pub released_ts: i64,
     pub verify_by_ts: i64,
     pub deliver_by_ts: i64,
-    pub warranty_end_ts: i64,
-
-    // Milestones (fixed array)
-    pub milestones_len: u8,
-    pub milestones: [Milestone; MAX_MILESTONES],
+    pub warranty_end_ts: i64, // 0 until the warranty starts
+    pub warranty_secs: i64, // per-escrow warranty period
+
+    // Pending two-party changes (propose → counterparty accepts)
+    pub pending_oracles: [Pubkey; MAX_ORACLES],
+    pub pending_oracles_len: u8,
+    pub pending_oracle_weights: [u16; MAX_ORACLES],
+    pub pending_quorum_weight: u32,
+    pub oracles_proposed_by: Pubkey,
+    pub oracles_proposal_expires_ts: i64,
+    pub pending_verify_by_ts: i64,
+    pub pending_deliver_by_ts: i64,
+    pub deadlines_proposed_by: Pubkey,
+    pub deadlines_proposal_expires_ts: i64,
+    pub proposal_ttl_secs: i64, // 0 = PROPOSAL_TTL_SECS
+
+    // Change orders (amend `amount`; both parties sign)
+    pub change_order_count: u16,
+    pub last_change_order_hash: [u8; 32],
+    pub pending_change_order_amount: u64,
+    pub pending_change_order_hash: [u8; 32],
+    pub change_order_proposed_by: Pubkey,
+    pub change_order_expires_ts: i64,
+
+    // Milestones (PDAs at [b"milestone", escrow, id]); next id + running sums of
+    // fixed amounts and of percentage shares
+    pub milestones_count: u16,
+    pub milestones_total: u64,
+    pub milestones_share_bps: u16,
+    // Released percentage milestones: their shares and the gross they actually paid
+    pub milestones_released_share_bps: u16,
+    pub milestones_released_amount: u64,
 
     // Evidence and attestations
     pub last_evidence_hash: [u8; 32],
@@ -937,6 +2407,19 @@ pub struct Escrow {
     // Cancel / dispute
     pub cancel_requested_by: Pubkey,
     pub dispute_open: bool,
+    pub disputes_count: u16, // current dispute number (vote PDA seed)
+    pub dispute_milestone_scoped: bool, // open dispute covers one milestone only
+    pub dispute_milestone_id: u16,
+    pub dispute_frozen: u64, // milestone payout frozen by a scoped dispute
+
+    // Arbiter panel override (arbiters_len == 0 → Config panel) and vote tally
+    pub arbiters: [Pubkey; MAX_ARBITERS],
+    pub arbiters_len: u8,
+    pub arbiter_threshold: u8,
+    pub dispute_refund_votes: u8,
+    pub dispute_release_votes: u8,
+    pub dispute_split_votes: u8,
+    pub dispute_split_bps: [u16; MAX_ARBITERS], // seller_pct_bps of each Split vote
 
     // NFT receipt option
     pub nft_enabled: bool,
@@ -946,6 +2429,22 @@ pub struct Escrow {
     pub in_transfer: bool,
     pub in_progress: bool,
     pub retention_released: bool,
+    pub retention_held: u64, // retention withheld from releases, still in the vault
+    pub retention_paid: u64, // retention already paid out to the seller
+
+    // Retention release schedule (empty = everything at warranty_end_ts)
+    pub retention_tranches_len: u8,
+    pub retention_tranche_ts: [i64; MAX_RETENTION_TRANCHES],
+    pub retention_tranche_bps: [u16; MAX_RETENTION_TRANCHES],
+    pub retention_tranches_released: u8, // bitmask over tranche indices
+
+    // Warranty claims against retention (one open claim at a time)
+    pub warranty_claim_status: u8, // WarrantyClaimStatus as u8
+    pub warranty_claim_amount: u64,
+    pub warranty_claim_hash: [u8; 32],
+    pub warranty_claims_count: u16,
+    pub retention_frozen: u64, // part of retention_held frozen by the open claim
+    pub retention_claimed: u64, // retention paid to the buyer on claims
     pub last_ix_nonce: u64,
 
     // Bumps
@@ -958,35 +2457,146 @@ impl Escrow {
     pub const SPACE: usize =
         8 + // disc
         8 + 32 + 32 + 32 + 32 + // ids
-        8 + 2 + 2 + 2 + 2 + 8 + // economics
-        1 + 1 + (32 * MAX_ORACLES) + // quorum/oracles
-        1 + 8 + 8 + 8 + 8 + 8 + 8 + // lifecycle
-        1 + (Milestone::SPACE * MAX_MILESTONES) + // milestones
+        8 + 2 + 2 + 2 + 2 + 2 + 8 + 8 + // economics
+        4 + 1 + (32 * MAX_ORACLES) + (2 * MAX_ORACLES) + // quorum/oracles/weights
+        1 + (8 * MAX_ORACLES) + 32 + 2 + (4 * MAX_ORACLES) + // delivery votes/attestation nonces
+        1 + 4 + 1 + // rejections/veto/escalation
+        1 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + // lifecycle
+        (32 * MAX_ORACLES) + 1 + (2 * MAX_ORACLES) + 4 + 32 + 8 + // pending oracles
+        8 + 8 + 32 + 8 + 8 + // pending deadlines/proposal TTL
+        2 + 32 + 8 + 32 + 32 + 8 + // change orders
+        2 + 8 + 2 + 2 + 8 + // milestones
         32 + 96 + 4 + // evidence
-        32 + 1 + // cancel/dispute
+        32 + 1 + 2 + 1 + 2 + 8 + // cancel/dispute
+        (32 * MAX_ARBITERS) + 1 + 1 + 1 + 1 + 1 + (2 * MAX_ARBITERS) + // arbiter panel/votes
         1 + 32 + // nft
-        1 + 1 + 1 + 8 + // guards/misc
+        1 + 1 + 1 + 8 + 8 + 8 + // guards/misc
+        1 + (8 * MAX_RETENTION_TRANCHES) + (2 * MAX_RETENTION_TRANCHES) + 1 + // retention schedule
+        1 + 8 + 32 + 2 + 8 + 8 + // warranty claims
         1 + 1 + // bumps
         256; // reserved
 
-    pub fn milestones(&self) -> &[Milestone] {
-        &self.milestones[..(self.milestones_len as usize)]
+    /// Scheduled milestone value for a given contract amount: fixed amounts, released
+    /// percentage milestones at what they paid, and the open shares of `amount`.
+    pub fn scheduled_total_for(&self, amount: u64) -> u64 {
+        let open_share_bps = self.milestones_share_bps.saturating_sub(self.milestones_released_share_bps);
+        self.milestones_total
+            .saturating_add(self.milestones_released_amount)
+            .saturating_add(mul_bps(amount, open_share_bps))
     }
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+#[account]
 pub struct Milestone {
-    pub id: u8,
-    pub amount: u64,
+    pub escrow: Pubkey,
+    pub id: u16,
+    pub amount: u64, // fixed amount (0 for percentage milestones)
+    pub share_bps: u16, // share of contract value (0 for fixed milestones)
     pub verified: bool,
     pub released: bool,
     pub verify_ts: i64,
     pub evidence_hash: [u8; 32],
-    pub reserved: [u8; 7],
+    // Per-milestone deadline & penalty schedule (PenaltyKind as u8)
+    pub due_ts: i64,
+    pub penalty_kind: u8,
+    pub penalty_bps: u16,
+    pub penalty_cap_bps: u16,
+    pub disputed: bool, // frozen by an open milestone-scoped dispute
+    // Oracle votes (bit i = escrow.oracles[i]), valid for oracle set `votes_oracle_set`
+    pub votes: u8,
+    pub vote_expiry: [i64; MAX_ORACLES],
+    pub votes_oracle_set: u16,
+    pub rejections: u8, // oracles that rejected this milestone (same set version as `votes`)
+    pub bump: u8,
+    pub escalated: bool, // rejections reached quorum_weight: open a scoped dispute; taken from `reserved`
+    pub reserved: [u8; 31],
 }
 impl Milestone {
-    pub const EMPTY: Milestone = Milestone { id: 0, amount: 0, verified: false, released: false, verify_ts: 0, evidence_hash: [0u8;32], reserved: [0u8;7] };
-    pub const SPACE: usize = 1 + 8 + 1 + 1 + 8 + 32 + 7;
+    pub const SPACE: usize =
+        8 + 32 + 2 + 8 + 2 + 1 + 1 + 8 + 32 + 8 + 1 + 2 + 2 + 1 + 1 + 1 + (8 * MAX_ORACLES) + 2 + 1 + 1 + 31;
+
+    /// Gross payout (before retention): the fixed amount, or the share of the escrow's
+    /// current contract value.
+    pub fn payout(&self, e: &Escrow) -> u64 {
+        if self.share_bps > 0 {
+            mul_bps(e.amount, self.share_bps)
+        } else {
+            self.amount
+        }
+    }
+}
+
+/// Case record for one dispute, at [b"dispute", escrow, dispute_no]. Every evidence
+/// submission is also emitted as an event so the full history can be rebuilt off-chain.
+#[account]
+pub struct DisputeRecord {
+    pub escrow: Pubkey,
+    pub dispute_no: u16,
+    pub claimant: Pubkey,
+    pub respondent: Pubkey,
+    pub reason_code: u16,
+    pub opened_ts: i64,
+    pub response_deadline_ts: i64,
+    pub responded_ts: i64, // 0 = respondent has not answered
+    // Evidence rounds (a round changes whenever the other side submits)
+    pub round: u8,
+    pub last_submitter: Pubkey,
+    pub claimant_submissions: u8,
+    pub respondent_submissions: u8,
+    pub claimant_evidence_hash: [u8; 32], // latest from each side
+    pub respondent_evidence_hash: [u8; 32],
+    // Arbiter panel (escrow's or Config's) snapshotted at opening
+    pub arbiters: [Pubkey; MAX_ARBITERS],
+    pub arbiters_len: u8,
+    pub arbiter_threshold: u8,
+    // Ruling (posted by the panel, possibly replaced on appeal, then executed)
+    pub ruled_ts: i64, // 0 = no ruling yet
+    pub ruling_outcome: u8, // DisputeOutcome as u8
+    pub ruling_seller_pct_bps: u16,
+    pub ruled_by: Pubkey, // arbiter whose vote reached the threshold (or the appeal arbiter); receives the arbiter fee
+    pub appeal_deadline_ts: i64,
+    pub resolved_ts: i64, // set by execute_ruling
+    pub outcome: u8, // DisputeOutcome as u8 once executed
+    pub by_default: bool,
+    // Appeal (appellant == default → not appealed)
+    pub appellant: Pubkey,
+    pub appealed_ts: i64,
+    pub appeal_decide_by_ts: i64, // undecided after this → the appeal lapses
+    pub appealed_seller_pct_bps: u16, // seller share under the appealed ruling
+    pub appeal_decided: bool,
+    pub appeal_bond_amount: u64,
+    pub appeal_bond_bump: u8,
+    // Scope: a single milestone (its payout frozen) or the whole escrow
+    pub milestone_scoped: bool,
+    pub milestone_id: u16,
+    pub frozen_amount: u64,
+    // Opener's bond, held in the token account at [b"bond", escrow, dispute_no]
+    pub bond_amount: u64,
+    pub bond_bump: u8,
+    pub bump: u8,
+}
+impl DisputeRecord {
+    pub const SPACE: usize =
+        8 + 32 + 2 + 32 + 32 + 2 + 8 + 8 + 8 + 1 + 32 + 1 + 1 + 32 + 32 + // parties/evidence
+        (32 * MAX_ARBITERS) + 1 + 1 + // panel snapshot
+        8 + 1 + 2 + 32 + 8 + 8 + 1 + 1 + // ruling
+        32 + 8 + 8 + 2 + 1 + 8 + 1 + // appeal
+        1 + 2 + 8 + 8 + 1 + 1; // scope/bond/bump
+}
+
+/// One arbiter's vote on one dispute, at [b"dispute_vote", escrow, dispute_no, arbiter].
+#[account]
+pub struct DisputeVote {
+    pub escrow: Pubkey,
+    pub dispute_no: u16,
+    pub arbiter: Pubkey,
+    pub outcome: u8, // DisputeOutcome as u8
+    pub seller_pct_bps: u16,
+    pub ts: i64,
+    pub bump: u8,
+}
+impl DisputeVote {
+    pub const SPACE: usize = 8 + 32 + 2 + 32 + 1 + 2 + 8 + 1;
 }
 
 #[account]
@@ -1126,18 +2736,93 @@ pub struct CreateEscrow<'info> {
     pub rent: Sysvar<'info, Rent>,
 }
 
-/* ======== Other context stubs you’ll need (minimal, compilable) ======== */
+/* ===== Role-gated contexts (buyer-only, seller-only, either party, both parties) ===== */
+
+/// Only the escrow's buyer may sign.
+#[derive(Accounts)]
+pub struct BuyerOnly<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(mut, has_one = buyer)]
+    pub escrow: Account<'info, Escrow>,
+}
 
+/// Either the escrow's buyer or its seller may sign as `actor`.
 #[derive(Accounts)]
 pub struct BuyerOrSeller<'info> {
     #[account(mut)]
     pub actor: Signer<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = actor.key() == escrow.buyer || actor.key() == escrow.seller @ EscrowError::Unauthorized
+    )]
     pub escrow: Account<'info, Escrow>,
 }
 
+/// Both the buyer and the seller must sign.
 #[derive(Accounts)]
-pub struct SellerOnly<'info> {
+pub struct BuyerAndSeller<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub seller: Signer<'info>,
+    #[account(mut, has_one = buyer, has_one = seller)]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct AddMilestone<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub seller: Signer<'info>,
+    #[account(mut, has_one = buyer, has_one = seller)]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        init,
+        payer = buyer,
+        space = Milestone::SPACE,
+        seeds = [b"milestone".as_ref(), escrow.key().as_ref(), escrow.milestones_count.to_be_bytes().as_ref()],
+        bump
+    )]
+    pub milestone: Account<'info, Milestone>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestone_id: u16)]
+pub struct EditMilestone<'info> {
+    pub buyer: Signer<'info>,
+    pub seller: Signer<'info>,
+    #[account(mut, has_one = buyer, has_one = seller)]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        seeds = [b"milestone".as_ref(), escrow.key().as_ref(), milestone_id.to_be_bytes().as_ref()],
+        bump = milestone.bump,
+        has_one = escrow
+    )]
+    pub milestone: Account<'info, Milestone>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestone_id: u16)]
+pub struct RemoveMilestone<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub seller: Signer<'info>,
+    #[account(mut, has_one = buyer, has_one = seller)]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        close = buyer,
+        seeds = [b"milestone".as_ref(), escrow.key().as_ref(), milestone_id.to_be_bytes().as_ref()],
+        bump = milestone.bump,
+        has_one = escrow
+    )]
+    pub milestone: Account<'info, Milestone>,
+}
+
+#[derive(Accounts)]
+pub struct SellerOnly<'info> {
     #[account(mut)]
     pub seller: Signer<'info>,
     #[account(mut, has_one = seller)]
@@ -1149,35 +2834,159 @@ pub struct RefundBuyer<'info> {
     #[account(mut)]
     pub escrow: Account<'info, Escrow>,
     /// CHECK: PDA vault authority
+    #[account(seeds = [b"vault".as_ref(), escrow.key().as_ref()], bump = escrow.vault_bump)]
     pub vault_authority: UncheckedAccount<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        associated_token::mint = escrow.mint,
+        associated_token::authority = vault_authority
+    )]
     pub vault_ata: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = buyer_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = buyer_ata.owner == escrow.buyer @ EscrowError::BadTokenOwner
+    )]
     pub buyer_ata: Account<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
 }
 
+#[derive(Accounts)]
+pub struct OracleVote<'info> {
+    pub oracle: Signer<'info>, // must be in escrow.oracles (checked in the handler)
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestone_id: u16)]
+pub struct OracleMilestoneVote<'info> {
+    pub oracle: Signer<'info>, // must be in escrow.oracles (checked in the handler)
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        seeds = [b"milestone".as_ref(), escrow.key().as_ref(), milestone_id.to_be_bytes().as_ref()],
+        bump = milestone.bump,
+        has_one = escrow
+    )]
+    pub milestone: Account<'info, Milestone>,
+}
+
 #[derive(Accounts)]
 pub struct VerifyWithQuorum<'info> {
     #[account(mut)]
     pub escrow: Account<'info, Escrow>,
+    /// CHECK: instructions sysvar (address-checked); holds the Ed25519 attestations
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct ReleaseCommon<'info> {
+#[instruction(milestone_id: u16)]
+pub struct VerifyMilestone<'info> {
     #[account(mut)]
     pub escrow: Account<'info, Escrow>,
-    /// CHECK
+    #[account(
+        mut,
+        seeds = [b"milestone".as_ref(), escrow.key().as_ref(), milestone_id.to_be_bytes().as_ref()],
+        bump = milestone.bump,
+        has_one = escrow
+    )]
+    pub milestone: Account<'info, Milestone>,
+    /// CHECK: instructions sysvar (address-checked); holds the Ed25519 attestations
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseCommon<'info> {
+    #[account(mut, has_one = config)]
+    pub escrow: Account<'info, Escrow>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: PDA vault authority
+    #[account(seeds = [b"vault".as_ref(), escrow.key().as_ref()], bump = escrow.vault_bump)]
     pub vault_authority: UncheckedAccount<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        associated_token::mint = escrow.mint,
+        associated_token::authority = vault_authority
+    )]
     pub vault_ata: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = seller_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = seller_ata.owner == escrow.seller @ EscrowError::BadTokenOwner
+    )]
     pub seller_ata: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = buyer_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = buyer_ata.owner == escrow.buyer @ EscrowError::BadTokenOwner
+    )]
     pub buyer_ata: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = treasury_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = treasury_ata.owner == config.treasury @ EscrowError::BadTokenOwner
+    )]
     pub treasury_ata: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = insurance_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = insurance_ata.owner == config.insurance_treasury @ EscrowError::BadTokenOwner
+    )]
+    pub insurance_ata: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestone_id: u16)]
+pub struct ReleaseMilestone<'info> {
+    #[account(mut, has_one = config)]
+    pub escrow: Account<'info, Escrow>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"milestone".as_ref(), escrow.key().as_ref(), milestone_id.to_be_bytes().as_ref()],
+        bump = milestone.bump,
+        has_one = escrow
+    )]
+    pub milestone: Account<'info, Milestone>,
+    /// CHECK: PDA vault authority
+    #[account(seeds = [b"vault".as_ref(), escrow.key().as_ref()], bump = escrow.vault_bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.mint,
+        associated_token::authority = vault_authority
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = seller_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = seller_ata.owner == escrow.seller @ EscrowError::BadTokenOwner
+    )]
+    pub seller_ata: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = buyer_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = buyer_ata.owner == escrow.buyer @ EscrowError::BadTokenOwner
+    )]
+    pub buyer_ata: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = treasury_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = treasury_ata.owner == config.treasury @ EscrowError::BadTokenOwner
+    )]
+    pub treasury_ata: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = insurance_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = insurance_ata.owner == config.insurance_treasury @ EscrowError::BadTokenOwner
+    )]
     pub insurance_ata: Account<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
 }
@@ -1186,36 +2995,332 @@ pub struct ReleaseCommon<'info> {
 pub struct ApproveCancel<'info> {
     #[account(mut)]
     pub actor: Signer<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = actor.key() == escrow.buyer || actor.key() == escrow.seller @ EscrowError::Unauthorized
+    )]
     pub escrow: Account<'info, Escrow>,
     /// CHECK: PDA vault authority
+    #[account(seeds = [b"vault".as_ref(), escrow.key().as_ref()], bump = escrow.vault_bump)]
     pub vault_authority: UncheckedAccount<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        associated_token::mint = escrow.mint,
+        associated_token::authority = vault_authority
+    )]
     pub vault_ata: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = buyer_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = buyer_ata.owner == escrow.buyer @ EscrowError::BadTokenOwner
+    )]
+    pub buyer_ata: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveChangeOrder<'info> {
     #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub seller: Signer<'info>,
+    #[account(mut, has_one = buyer, has_one = seller)]
+    pub escrow: Account<'info, Escrow>,
+    /// CHECK: PDA vault authority
+    #[account(seeds = [b"vault".as_ref(), escrow.key().as_ref()], bump = escrow.vault_bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.mint,
+        associated_token::authority = vault_authority
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = buyer_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = buyer_ata.owner == escrow.buyer @ EscrowError::BadTokenOwner
+    )]
+    pub buyer_ata: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptWarrantyClaim<'info> {
+    pub seller: Signer<'info>,
+    #[account(mut, has_one = seller)]
+    pub escrow: Account<'info, Escrow>,
+    /// CHECK: PDA vault authority
+    #[account(seeds = [b"vault".as_ref(), escrow.key().as_ref()], bump = escrow.vault_bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.mint,
+        associated_token::authority = vault_authority
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = buyer_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = buyer_ata.owner == escrow.buyer @ EscrowError::BadTokenOwner
+    )]
     pub buyer_ata: Account<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct ArbiterResolve<'info> {
-    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = arbiter)]
+pub struct ResolveWarrantyClaim<'info> {
+    #[account(seeds = [b"config"], bump = config.bump, has_one = arbiter)]
     pub config: Account<'info, Config>,
     pub arbiter: Signer<'info>,
-    #[account(mut)]
+    #[account(mut, has_one = config)]
     pub escrow: Account<'info, Escrow>,
-    /// CHECK
+    /// CHECK: PDA vault authority
+    #[account(seeds = [b"vault".as_ref(), escrow.key().as_ref()], bump = escrow.vault_bump)]
     pub vault_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.mint,
+        associated_token::authority = vault_authority
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = buyer_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = buyer_ata.owner == escrow.buyer @ EscrowError::BadTokenOwner
+    )]
+    pub buyer_ata: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct OpenDispute<'info> {
     #[account(mut)]
+    pub actor: Signer<'info>, // claimant; pays for the dispute record and bond vault
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        has_one = config,
+        has_one = mint,
+        constraint = actor.key() == escrow.buyer || actor.key() == escrow.seller @ EscrowError::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = actor,
+        space = DisputeRecord::SPACE,
+        seeds = [b"dispute".as_ref(), escrow.key().as_ref(), (escrow.disputes_count + 1).to_be_bytes().as_ref()],
+        bump
+    )]
+    pub dispute: Account<'info, DisputeRecord>,
+    /// Optional: scope the dispute to this milestone.
+    #[account(mut, has_one = escrow)]
+    pub milestone: Option<Account<'info, Milestone>>,
+    /// CHECK: PDA vault authority
+    #[account(seeds = [b"vault".as_ref(), escrow.key().as_ref()], bump = escrow.vault_bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(
+        associated_token::mint = escrow.mint,
+        associated_token::authority = vault_authority
+    )]
     pub vault_ata: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = actor,
+        seeds = [b"bond".as_ref(), escrow.key().as_ref(), (escrow.disputes_count + 1).to_be_bytes().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = vault_authority
+    )]
+    pub bond_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = actor_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = actor_ata.owner == actor.key() @ EscrowError::BadTokenOwner
+    )]
+    pub actor_ata: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitDisputeEvidence<'info> {
+    pub actor: Signer<'info>,
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        seeds = [b"dispute".as_ref(), escrow.key().as_ref(), escrow.disputes_count.to_be_bytes().as_ref()],
+        bump = dispute.bump,
+        constraint = actor.key() == dispute.claimant || actor.key() == dispute.respondent @ EscrowError::Unauthorized
+    )]
+    pub dispute: Account<'info, DisputeRecord>,
+}
+
+#[derive(Accounts)]
+pub struct CastDisputeVote<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
     #[account(mut)]
-    pub buyer_ata: Account<'info, TokenAccount>,
+    pub arbiter: Signer<'info>, // panel membership checked in the handler; pays for the vote
+    #[account(mut, has_one = config)]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        seeds = [b"dispute".as_ref(), escrow.key().as_ref(), escrow.disputes_count.to_be_bytes().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, DisputeRecord>,
+    #[account(
+        init_if_needed,
+        payer = arbiter,
+        space = DisputeVote::SPACE,
+        seeds = [
+            b"dispute_vote".as_ref(),
+            escrow.key().as_ref(),
+            escrow.disputes_count.to_be_bytes().as_ref(),
+            arbiter.key().as_ref()
+        ],
+        bump
+    )]
+    pub vote: Account<'info, DisputeVote>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AppealRuling<'info> {
     #[account(mut)]
+    pub actor: Signer<'info>, // appellant; pays for the appeal bond vault
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(has_one = config, has_one = mint)]
+    pub escrow: Account<'info, Escrow>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"dispute".as_ref(), escrow.key().as_ref(), escrow.disputes_count.to_be_bytes().as_ref()],
+        bump = dispute.bump,
+        constraint = actor.key() == dispute.claimant || actor.key() == dispute.respondent @ EscrowError::Unauthorized
+    )]
+    pub dispute: Account<'info, DisputeRecord>,
+    /// CHECK: PDA vault authority
+    #[account(seeds = [b"vault".as_ref(), escrow.key().as_ref()], bump = escrow.vault_bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = actor,
+        seeds = [b"appeal_bond".as_ref(), escrow.key().as_ref(), escrow.disputes_count.to_be_bytes().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = vault_authority
+    )]
+    pub appeal_bond_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = actor_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = actor_ata.owner == actor.key() @ EscrowError::BadTokenOwner
+    )]
+    pub actor_ata: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DecideAppeal<'info> {
+    #[account(seeds = [b"config"], bump = config.bump, has_one = appeal_arbiter)]
+    pub config: Account<'info, Config>,
+    pub appeal_arbiter: Signer<'info>,
+    #[account(has_one = config)]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        seeds = [b"dispute".as_ref(), escrow.key().as_ref(), escrow.disputes_count.to_be_bytes().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, DisputeRecord>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRuling<'info> {
+    pub caller: Signer<'info>, // permissionless
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, has_one = config)]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        seeds = [b"dispute".as_ref(), escrow.key().as_ref(), escrow.disputes_count.to_be_bytes().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, DisputeRecord>,
+    /// Required for milestone-scoped disputes.
+    #[account(
+        mut,
+        seeds = [b"milestone".as_ref(), escrow.key().as_ref(), escrow.dispute_milestone_id.to_be_bytes().as_ref()],
+        bump = milestone.bump
+    )]
+    pub milestone: Option<Account<'info, Milestone>>,
+    /// CHECK: PDA vault authority
+    #[account(seeds = [b"vault".as_ref(), escrow.key().as_ref()], bump = escrow.vault_bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.mint,
+        associated_token::authority = vault_authority
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"bond".as_ref(), escrow.key().as_ref(), escrow.disputes_count.to_be_bytes().as_ref()],
+        bump = dispute.bond_bump,
+        token::mint = escrow.mint,
+        token::authority = vault_authority
+    )]
+    pub bond_vault: Account<'info, TokenAccount>,
+    /// CHECK: receives the bond vault's rent when it is closed
+    #[account(mut, address = dispute.claimant)]
+    pub claimant: UncheckedAccount<'info>,
+    /// Required if the ruling was appealed.
+    #[account(
+        mut,
+        seeds = [b"appeal_bond".as_ref(), escrow.key().as_ref(), escrow.disputes_count.to_be_bytes().as_ref()],
+        bump = dispute.appeal_bond_bump,
+        token::mint = escrow.mint,
+        token::authority = vault_authority
+    )]
+    pub appeal_bond_vault: Option<Account<'info, TokenAccount>>,
+    /// CHECK: receives the appeal bond vault's rent when it is closed
+    #[account(mut, address = dispute.appellant)]
+    pub appellant: Option<UncheckedAccount<'info>>,
+    #[account(
+        mut,
+        constraint = buyer_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = buyer_ata.owner == escrow.buyer @ EscrowError::BadTokenOwner
+    )]
+    pub buyer_ata: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = seller_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = seller_ata.owner == escrow.seller @ EscrowError::BadTokenOwner
+    )]
     pub seller_ata: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = treasury_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = treasury_ata.owner == config.treasury @ EscrowError::BadTokenOwner
+    )]
     pub treasury_ata: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = insurance_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = insurance_ata.owner == config.insurance_treasury @ EscrowError::BadTokenOwner
+    )]
     pub insurance_ata: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = arbiter_ata.mint == escrow.mint @ EscrowError::BadMint,
+        constraint = arbiter_ata.owner == dispute.ruled_by @ EscrowError::BadTokenOwner
+    )]
+    pub arbiter_ata: Account<'info, TokenAccount>, // the panel arbiter that posted the ruling
     pub token_program: Program<'info, Token>,
 }
 
@@ -1269,32 +3374,64 @@ pub struct FinalizeReceiptNft<'info> {
 #[derive(Accounts)]
 pub struct ProcessTimeouts<'info> {
     pub caller: Signer<'info>,
+    pub token_program: Program<'info, Token>,
 }
 
 /* =============================== Events =================================== */
 
 #[event] pub struct ConfigUpdated { pub fee_bps: u16, pub insurance_bps: u16, pub retention_bps: u16, pub warranty_days: i64, pub quorum_m: u8 }
+#[event] pub struct WarrantyBoundsUpdated { pub warranty_days: i64, pub min_warranty_days: i64, pub max_warranty_days: i64 }
+#[event] pub struct ArbiterPanelSet { pub project_id: Option<u64>, pub arbiters: Vec<Pubkey>, pub threshold: u8 }
+#[event] pub struct DisputeBondSet { pub amount: u64, pub forfeit_to: BondForfeit }
+#[event] pub struct ArbiterFeeSet { pub fee_bps: u16, pub payer: ArbiterFeePayer }
+#[event] pub struct AppealTermsSet { pub window_secs: i64, pub appeal_arbiter: Pubkey, pub appeal_bond: u64 }
+#[event] pub struct DisputeResponseWindowSet { pub response_secs: i64 }
 #[event] pub struct ConfigAuthorityProposed { pub proposed: Pubkey }
 #[event] pub struct ConfigAuthorityTransferred { pub new_authority: Pubkey }
 
-#[event] pub struct EscrowCreated { pub project_id: u64, pub buyer: Pubkey, pub seller: Pubkey, pub mint: Pubkey, pub amount: u64, pub quorum_m: u8, pub price_snapshot_1e6: u64 }
+#[event] pub struct EscrowCreated { pub project_id: u64, pub buyer: Pubkey, pub seller: Pubkey, pub mint: Pubkey, pub amount: u64, pub quorum_weight: u32, pub price_snapshot_1e6: u64 }
 #[event] pub struct DeadlinesSet { pub project_id: u64, pub verify_by_ts: i64, pub deliver_by_ts: i64 }
+#[event] pub struct DeadlinesProposed { pub project_id: u64, pub by: Pubkey, pub verify_by_ts: i64, pub deliver_by_ts: i64, pub expires_ts: i64 }
+#[event] pub struct LatePenaltySet { pub project_id: u64, pub grace_secs: i64, pub bps_per_day: u16, pub cap_bps: u16 }
 #[event] pub struct ProgressMarked { pub project_id: u64, pub ts: i64 }
 #[event] pub struct ExpiredAndRefunded { pub project_id: u64, pub amount: u64 }
-#[event] pub struct DeliveryVerified { pub project_id: u64, pub quorum_votes: u8, pub when: i64 }
-
-#[event] pub struct MilestoneAdded { pub project_id: u64, pub id: u8, pub amount: u64, pub evidence_hash: [u8;32] }
-#[event] pub struct MilestoneVerified { pub project_id: u64, pub id: u8, pub when: i64 }
-#[event] pub struct MilestoneReleased { pub project_id: u64, pub id: u8, pub gross: u64, pub fee_cut: u64, pub insurance_cut: u64, pub seller_received: u64 }
-
-#[event] pub struct PaymentReleased { pub project_id: u64, pub seller: Pubkey, pub amount: u64, pub fee_cut: u64, pub insurance_cut: u64, pub seller_received: u64, pub when: i64 }
-#[event] pub struct RetentionReleased { pub project_id: u64, pub gross: u64, pub fee_cut: u64, pub insurance_cut: u64, pub seller_received: u64 }
+#[event] pub struct ChangeOrderSubmitted { pub project_id: u64, pub by: Pubkey, pub change_order_no: u16, pub old_amount: u64, pub new_amount: u64, pub doc_hash: [u8;32], pub expires_ts: i64 }
+#[event] pub struct ChangeOrderApplied { pub project_id: u64, pub change_order_no: u16, pub old_amount: u64, pub new_amount: u64, pub retention: u64, pub doc_hash: [u8;32] }
+#[event] pub struct WarrantyStarted { pub project_id: u64, pub start_ts: i64, pub end_ts: i64 }
+#[event] pub struct OracleVotesRecorded { pub project_id: u64, pub milestone_id: Option<u16>, pub live_votes: u8, pub live_weight: u32, pub quorum_weight: u32, pub vetoed: bool }
+#[event] pub struct OracleVoteWithdrawn { pub project_id: u64, pub milestone_id: Option<u16>, pub oracle: Pubkey, pub nonce: u32 }
+#[event] pub struct OracleRejected { pub project_id: u64, pub milestone_id: Option<u16>, pub oracle: Pubkey, pub reason_code: u16, pub evidence_hash: [u8; 32], pub rejection_weight: u32 }
+#[event] pub struct OracleRejectionCleared { pub project_id: u64, pub milestone_id: Option<u16>, pub oracle: Pubkey }
+#[event] pub struct VerificationEscalated { pub project_id: u64, pub milestone_id: Option<u16>, pub rejection_weight: u32 }
+#[event] pub struct EscalationCleared { pub project_id: u64, pub milestone_id: Option<u16>, pub state: u8 }
+#[event] pub struct VetoWeightSet { pub project_id: u64, pub veto_weight: u32 }
+#[event] pub struct ProposalTtlSet { pub project_id: u64, pub ttl_secs: i64 }
+#[event] pub struct DeliveryVerified { pub project_id: u64, pub quorum_votes: u8, pub quorum_weight: u32, pub when: i64 }
+
+#[event] pub struct MilestoneAdded { pub project_id: u64, pub id: u16, pub amount: u64, pub share_bps: u16, pub evidence_hash: [u8;32], pub due_ts: i64 }
+#[event] pub struct MilestoneUpdated { pub project_id: u64, pub id: u16, pub old_amount: u64, pub new_amount: u64, pub share_bps: u16, pub evidence_hash: [u8;32] }
+#[event] pub struct MilestoneRemoved { pub project_id: u64, pub id: u16, pub amount: u64 }
+#[event] pub struct MilestoneVerified { pub project_id: u64, pub id: u16, pub when: i64 }
+#[event] pub struct MilestoneReleased { pub project_id: u64, pub id: u16, pub gross: u64, pub retention_cut: u64, pub fee_cut: u64, pub insurance_cut: u64, pub penalty: u64, pub seller_received: u64 }
+
+#[event] pub struct PaymentReleased { pub project_id: u64, pub seller: Pubkey, pub amount: u64, pub retention_cut: u64, pub fee_cut: u64, pub insurance_cut: u64, pub penalty: u64, pub seller_received: u64, pub when: i64 }
+#[event] pub struct RetentionScheduleSet { pub project_id: u64, pub release_ts: Vec<i64>, pub bps: Vec<u16> }
+#[event] pub struct RetentionReleased { pub project_id: u64, pub gross: u64, pub fee_cut: u64, pub insurance_cut: u64, pub seller_received: u64, pub tranches_released: u8, pub fully_released: bool }
+
+#[event] pub struct WarrantyClaimFiled { pub project_id: u64, pub claim_no: u16, pub amount: u64, pub evidence_hash: [u8;32] }
+#[event] pub struct WarrantyClaimContested { pub project_id: u64, pub claim_no: u16 }
+#[event] pub struct WarrantyClaimSettled { pub project_id: u64, pub claim_no: u16, pub buyer_received: u64, pub returned_to_retention: u64, pub by_arbiter: bool }
 
 #[event] pub struct CancelRequested { pub project_id: u64, pub by: Pubkey }
 #[event] pub struct CancelApprovedAndRefunded { pub project_id: u64, pub amount: u64 }
 
-#[event] pub struct DisputeOpened { pub project_id: u64, pub reason_code: u16, pub evidence_hash: [u8;32] }
-#[event] pub struct DisputeResolved { pub project_id: u64, pub outcome: DisputeOutcome, pub buyer_received: u64, pub seller_received: u64, pub fee_cut: u64, pub insurance_cut: u64 }
+#[event] pub struct DisputeOpened { pub project_id: u64, pub dispute_no: u16, pub milestone_id: Option<u16>, pub frozen_amount: u64, pub bond: u64, pub claimant: Pubkey, pub respondent: Pubkey, pub reason_code: u16, pub evidence_hash: [u8;32], pub response_deadline_ts: i64 }
+#[event] pub struct DisputeEvidenceSubmitted { pub project_id: u64, pub dispute_no: u16, pub by: Pubkey, pub round: u8, pub hash: [u8;32], pub uri_prefix: [u8;96], pub when: i64 }
+#[event] pub struct DisputeVoteCast { pub project_id: u64, pub dispute_no: u16, pub arbiter: Pubkey, pub outcome: DisputeOutcome, pub seller_pct_bps: u16, pub votes_for_outcome: u8, pub threshold: u8, pub replaced: bool }
+#[event] pub struct RulingPosted { pub project_id: u64, pub dispute_no: u16, pub outcome: DisputeOutcome, pub seller_pct_bps: u16, pub by_default: bool, pub appeal_deadline_ts: i64 }
+#[event] pub struct RulingAppealed { pub project_id: u64, pub dispute_no: u16, pub by: Pubkey, pub bond: u64, pub evidence_hash: [u8;32], pub decide_by_ts: i64 }
+#[event] pub struct AppealDecided { pub project_id: u64, pub dispute_no: u16, pub outcome: DisputeOutcome, pub seller_pct_bps: u16 }
+#[event] pub struct DisputeResolved { pub project_id: u64, pub dispute_no: u16, pub milestone_id: Option<u16>, pub outcome: DisputeOutcome, pub seller_pct_bps: u16, pub by_default: bool, pub bond: u64, pub bond_refunded: bool, pub appealed: bool, pub appeal_lapsed: bool, pub appeal_bond: u64, pub appeal_bond_refunded: bool, pub retention_cut: u64, pub buyer_received: u64, pub seller_received: u64, pub fee_cut: u64, pub insurance_cut: u64, pub arbiter_fee: u64 }
 
 #[event] pub struct EvidenceAttached { pub project_id: u64, pub hash: [u8;32], pub uri_prefix: [u8;96] }
 #[event] pub struct Attested { pub project_id: u64, pub attester: Pubkey, pub hash: [u8;32], pub uri_prefix: [u8;96] }
@@ -1302,8 +3439,11 @@ pub struct ProcessTimeouts<'info> {
 #[event] pub struct ReceiptNftMinted { pub project_id: u64, pub mint: Pubkey, pub to: Pubkey }
 #[event] pub struct ReceiptNftFinalized { pub project_id: u64, pub mint: Pubkey, pub burned: bool }
 
-#[event] pub struct TimeoutsProcessed { pub processed: u8 }
-#[event] pub struct OraclesUpdated { pub project_id: u64, pub quorum_m: u8, pub count: u8 }
+#[event] pub struct TimeoutsProcessed { pub processed: u8, pub project_ids: Vec<u64> }
+#[event] pub struct OraclesUpdated { pub project_id: u64, pub quorum_weight: u32, pub count: u8 }
+#[event] pub struct OraclesProposed { pub project_id: u64, pub by: Pubkey, pub oracles: Vec<Pubkey>, pub weights: Vec<u16>, pub quorum_weight: u32, pub expires_ts: i64 }
+#[event] pub struct ProposalAccepted { pub project_id: u64, pub kind: ChangeKind, pub by: Pubkey }
+#[event] pub struct ProposalRejected { pub project_id: u64, pub kind: ChangeKind, pub by: Pubkey }
 #[event] pub struct SellerUpdated { pub project_id: u64, pub new_seller: Pubkey }
 
 /* ================================ Errors ================================== */
@@ -1311,8 +3451,10 @@ pub struct ProcessTimeouts<'info> {
 #[error_code]
 pub enum EscrowError {
     #[msg("Amount must be greater than zero.")] ZeroAmount,
-    #[msg("Quorum must be at least 1.")] BadQuorum,
+    #[msg("Quorum must be at least 1 and at most the total oracle weight.")] BadQuorum,
     #[msg("Too many oracles.")] TooManyOracles,
+    #[msg("Oracle list must not contain duplicate or default pubkeys.")] BadOracle,
+    #[msg("Each oracle needs exactly one non-zero weight.")] BadOracleWeights,
     #[msg("Nonce must increase.")] BadNonce,
     #[msg("Escrow is in a wrong state for this action.")] BadState,
     #[msg("Escrow not expired.")] NotExpired,
@@ -1336,6 +3478,46 @@ pub enum EscrowError {
     #[msg("Milestones exceed total escrow amount.")] MilestoneOverTotal,
     #[msg("Bad authority accept.")] BadAuthorityAccept,
     #[msg("Reentrancy detected.")] Reentrancy,
+    #[msg("Token account mint does not match escrow mint.")] BadMint,
+    #[msg("Token account owner is not the expected party.")] BadTokenOwner,
+    #[msg("No pending proposal.")] NoPendingProposal,
+    #[msg("A proposal is already pending.")] ProposalPending,
+    #[msg("Proposal expired.")] ProposalExpired,
+    #[msg("Change order does not change the amount.")] ChangeOrderNoop,
+    #[msg("Change order document hash does not match the pending one.")] ChangeOrderMismatch,
+    #[msg("Basis points must be at most 10,000.")] BadBps,
+    #[msg("Grace period must not be negative.")] BadGracePeriod,
+    #[msg("Milestone must have either a fixed amount or a share, not both.")] BadMilestoneAmount,
+    #[msg("Milestone shares exceed 10,000 bps.")] MilestoneSharesOver,
+    #[msg("Retention schedule must have ascending timestamps and bps summing to 10,000.")] BadRetentionSchedule,
+    #[msg("A warranty claim is already open.")] ClaimAlreadyOpen,
+    #[msg("No open warranty claim.")] NoOpenClaim,
+    #[msg("Claim exceeds unclaimed retention.")] ClaimOverRetention,
+    #[msg("Warranty period has ended.")] WarrantyEnded,
+    #[msg("Warranty period has not started.")] WarrantyNotStarted,
+    #[msg("Warranty period outside configured bounds.")] BadWarrantyPeriod,
+    #[msg("Arbiter panel must have 1..=5 distinct arbiters and 1 <= threshold <= panel size.")] BadArbiterPanel,
+    #[msg("Signer is not on the arbiter panel.")] NotArbiter,
+    #[msg("Respondent may still respond; ruling not yet allowed.")] AwaitingResponse,
+    #[msg("Milestone is frozen by an open dispute.")] MilestoneDisputed,
+    #[msg("A ruling has already been posted for this dispute.")] RulingAlreadyPosted,
+    #[msg("No ruling has been posted.")] NoRuling,
+    #[msg("Appeals are disabled.")] AppealsDisabled,
+    #[msg("Ruling already appealed.")] AlreadyAppealed,
+    #[msg("Appeal window has closed.")] AppealWindowClosed,
+    #[msg("Appeal window is still open.")] AppealWindowOpen,
+    #[msg("No appeal awaiting a decision.")] NoPendingAppeal,
+    #[msg("Appeal window must not be negative.")] BadAppealWindow,
+    #[msg("Signer is not an oracle for this escrow.")] NotOracle,
+    #[msg("No valid oracle vote.")] NoOracleVote,
+    #[msg("Live oracle votes refer to different evidence.")] EvidenceMismatch,
+    #[msg("No rejection from this oracle.")] NoOracleRejection,
+    #[msg("Veto weight exceeds the total oracle weight.")] BadVetoWeight,
+    #[msg("Milestone already released.")] MilestoneAlreadyReleased,
+    #[msg("Appeal decision deadline has passed.")] AppealLapsed,
+    #[msg("Appeal is awaiting the appeal arbiter's decision.")] AppealPending,
+    #[msg("Proposal TTL must not be negative.")] BadProposalTtl,
+    #[msg("Dispute response window must not be negative.")] BadResponseWindow,
 }
 
 /* ============================== Helpers/Utils ============================== */
@@ -1352,6 +3534,237 @@ fn calc_retention(total: u64, retention_bps: u16) -> u64 {
     mul_bps(total, retention_bps)
 }
 
+/// Late penalty (bps of the seller payout) for a deadline missed at `now`.
+/// `Flat` applies `bps` once late; `PerDay` accrues `bps` per started day, capped at `cap_bps`.
+fn late_penalty_bps(kind: u8, bps: u16, cap_bps: u16, due_ts: i64, now: i64) -> u16 {
+    if due_ts == 0 || now <= due_ts {
+        return 0;
+    }
+    if kind == PenaltyKind::Flat as u8 {
+        bps
+    } else if kind == PenaltyKind::PerDay as u8 {
+        let days_late = (now - due_ts + SECONDS_PER_DAY - 1) / SECONDS_PER_DAY;
+        (days_late as u64).saturating_mul(bps as u64).min(cap_bps as u64) as u16
+    } else {
+        0
+    }
+}
+
+/// When a proposal made at `now` stops being acceptable.
+fn proposal_expiry(e: &Escrow, now: i64) -> i64 {
+    let ttl = if e.proposal_ttl_secs > 0 { e.proposal_ttl_secs } else { PROPOSAL_TTL_SECS };
+    now.saturating_add(ttl)
+}
+
+fn clear_oracles_proposal(e: &mut Account<Escrow>) {
+    e.pending_oracles = [Pubkey::default(); MAX_ORACLES];
+    e.pending_oracles_len = 0;
+    e.pending_oracle_weights = [0u16; MAX_ORACLES];
+    e.pending_quorum_weight = 0;
+    e.oracles_proposed_by = Pubkey::default();
+    e.oracles_proposal_expires_ts = 0;
+}
+
+fn clear_deadlines_proposal(e: &mut Account<Escrow>) {
+    e.pending_verify_by_ts = 0;
+    e.pending_deliver_by_ts = 0;
+    e.deadlines_proposed_by = Pubkey::default();
+    e.deadlines_proposal_expires_ts = 0;
+}
+
+fn clear_change_order(e: &mut Account<Escrow>) {
+    e.pending_change_order_amount = 0;
+    e.pending_change_order_hash = [0u8; 32];
+    e.change_order_proposed_by = Pubkey::default();
+    e.change_order_expires_ts = 0;
+}
+
+/// Escrow-wide liquidated damages: `late_penalty_bps` per started day past
+/// `deliver_by_ts + late_grace_secs`, capped at `late_penalty_cap_bps`.
+fn escrow_late_penalty_bps(e: &Escrow, now: i64) -> u16 {
+    if e.deliver_by_ts == 0 {
+        return 0;
+    }
+    late_penalty_bps(
+        PenaltyKind::PerDay as u8,
+        e.late_penalty_bps,
+        e.late_penalty_cap_bps,
+        e.deliver_by_ts.saturating_add(e.late_grace_secs),
+        now,
+    )
+}
+
+/// Sum of matured retention tranche bps at `now`, and the bitmask of matured tranches.
+/// Without a schedule, a single 100% tranche matures at `warranty_end_ts`.
+fn matured_retention(e: &Escrow, now: i64) -> (u16, u8) {
+    if e.retention_tranches_len == 0 {
+        return if e.warranty_end_ts > 0 && now >= e.warranty_end_ts { (10_000, 1) } else { (0, 0) };
+    }
+    let mut bps: u16 = 0;
+    let mut mask: u8 = 0;
+    for i in 0..(e.retention_tranches_len as usize) {
+        if now >= e.retention_tranche_ts[i] {
+            bps = bps.saturating_add(e.retention_tranche_bps[i]);
+            mask |= 1 << i;
+        }
+    }
+    (bps, mask)
+}
+
+/// Bitmask of the `matured` tranches whose cumulative share of all retention withheld has
+/// been paid out (a tranche short-paid because of a frozen claim stays open).
+fn paid_retention_tranches(e: &Escrow, matured: u8) -> u8 {
+    let basis = e.retention_held.saturating_add(e.retention_paid);
+    let len = (e.retention_tranches_len as usize).max(1);
+    let mut cum_bps: u16 = 0;
+    let mut mask: u8 = 0;
+    for i in 0..len {
+        cum_bps = cum_bps.saturating_add(if e.retention_tranches_len == 0 { 10_000 } else { e.retention_tranche_bps[i] });
+        let due = if cum_bps >= 10_000 { basis } else { mul_bps(basis, cum_bps) };
+        if matured & (1 << i) != 0 && e.retention_paid >= due {
+            mask |= 1 << i;
+        }
+    }
+    mask
+}
+
+/// Start the warranty clock once (at verified delivery or final release).
+fn start_warranty(e: &mut Account<Escrow>, start_ts: i64) {
+    if e.warranty_end_ts != 0 {
+        return;
+    }
+    e.warranty_end_ts = start_ts.saturating_add(e.warranty_secs);
+    emit!(WarrantyStarted { project_id: e.project_id, start_ts, end_ts: e.warranty_end_ts });
+}
+
+/// Mark a milestone paid. A percentage milestone stops tracking `amount`: change orders
+/// keep counting it at the gross it paid (see `Escrow::scheduled_total_for`).
+fn mark_milestone_released(e: &mut Account<Escrow>, m: &mut Account<Milestone>, gross: u64) {
+    m.released = true;
+    if m.share_bps > 0 {
+        e.milestones_released_share_bps = e.milestones_released_share_bps.saturating_add(m.share_bps);
+        e.milestones_released_amount = e.milestones_released_amount.saturating_add(gross);
+    }
+}
+
+/// Close the open warranty claim: `paid_to_buyer` leaves retention, the rest is unfrozen.
+fn settle_warranty_claim(e: &mut Account<Escrow>, paid_to_buyer: u64) {
+    e.retention_frozen = e.retention_frozen.saturating_sub(e.warranty_claim_amount);
+    e.retention_held = e.retention_held.saturating_sub(paid_to_buyer);
+    e.retention_claimed = e.retention_claimed.saturating_add(paid_to_buyer);
+    e.warranty_claim_status = WarrantyClaimStatus::None as u8;
+    e.warranty_claim_amount = 0;
+}
+
+fn validate_oracle_set(oracles: &[Pubkey], weights: &[u16], quorum_weight: u32) -> Result<()> {
+    require!(oracles.len() <= MAX_ORACLES, EscrowError::TooManyOracles);
+    require!(weights.len() == oracles.len() && !weights.contains(&0), EscrowError::BadOracleWeights);
+    let total: u32 = weights.iter().map(|w| *w as u32).sum();
+    require!(quorum_weight >= QUORUM_MIN as u32 && quorum_weight <= total, EscrowError::BadQuorum);
+    for (i, pk) in oracles.iter().enumerate() {
+        require!(*pk != Pubkey::default(), EscrowError::BadOracle);
+        require!(!oracles[..i].contains(pk), EscrowError::BadOracle);
+    }
+    Ok(())
+}
+
+fn validate_arbiter_panel(arbiters: &[Pubkey], threshold: u8) -> Result<()> {
+    require!(!arbiters.is_empty() && arbiters.len() <= MAX_ARBITERS, EscrowError::BadArbiterPanel);
+    require!(threshold >= 1 && threshold as usize <= arbiters.len(), EscrowError::BadArbiterPanel);
+    for (i, pk) in arbiters.iter().enumerate() {
+        require!(*pk != Pubkey::default(), EscrowError::BadArbiterPanel);
+        require!(!arbiters[..i].contains(pk), EscrowError::BadArbiterPanel);
+    }
+    Ok(())
+}
+
+/// Effective dispute panel and threshold: the escrow override if set, else the Config panel.
+fn arbiter_panel<'a>(cfg: &'a Config, e: &'a Escrow) -> (&'a [Pubkey], u8) {
+    if e.arbiters_len > 0 {
+        (&e.arbiters[..e.arbiters_len as usize], e.arbiter_threshold)
+    } else {
+        (&cfg.arbiters[..cfg.arbiters_len as usize], cfg.arbiter_threshold)
+    }
+}
+
+/// Buyer's and seller's parts of the arbiter fee (`arbiter_fee_bps` of the disputed
+/// amount) per `arbiter_fee_payer`. A side is never charged more than it is awarded; the
+/// shortfall comes out of the other side's award, so the fee is always paid in full
+/// (e.g. a full refund with `Loser`: the seller gets nothing, so the buyer pays).
+fn arbiter_fee_shares(cfg: &Config, total: u64, buyer_amt: u64, seller_amt: u64) -> (u64, u64) {
+    let fee = mul_bps(total, cfg.arbiter_fee_bps);
+    let half = fee / 2;
+    let (buyer_part, seller_part) = if cfg.arbiter_fee_payer == ArbiterFeePayer::Buyer as u8 {
+        (fee, 0)
+    } else if cfg.arbiter_fee_payer == ArbiterFeePayer::Seller as u8 {
+        (0, fee)
+    } else if cfg.arbiter_fee_payer == ArbiterFeePayer::Loser as u8 && buyer_amt < seller_amt {
+        (fee, 0)
+    } else if cfg.arbiter_fee_payer == ArbiterFeePayer::Loser as u8 && seller_amt < buyer_amt {
+        (0, fee)
+    } else {
+        (half, fee - half)
+    };
+    // buyer_amt + seller_amt == total >= fee, so the remainder always fits the seller's award
+    let buyer_fee = buyer_part.min(buyer_amt).max(fee.saturating_sub(seller_amt));
+    (buyer_fee, fee - buyer_fee)
+}
+
+fn dispute_outcome_from_u8(v: u8) -> DisputeOutcome {
+    if v == DisputeOutcome::Refund as u8 {
+        DisputeOutcome::Refund
+    } else if v == DisputeOutcome::Release as u8 {
+        DisputeOutcome::Release
+    } else {
+        DisputeOutcome::Split
+    }
+}
+
+/// Seller's share (bps) under a ruling, for comparing an appealed ruling with the final one.
+fn seller_share_bps(outcome: u8, seller_pct_bps: u16) -> u16 {
+    match dispute_outcome_from_u8(outcome) {
+        DisputeOutcome::Refund => 0,
+        DisputeOutcome::Release => 10_000,
+        DisputeOutcome::Split => seller_pct_bps,
+    }
+}
+
+fn clear_dispute_votes(e: &mut Account<Escrow>) {
+    e.dispute_refund_votes = 0;
+    e.dispute_release_votes = 0;
+    e.dispute_split_votes = 0;
+    e.dispute_split_bps = [0u16; MAX_ARBITERS];
+}
+
+/// Take an arbiter's earlier vote out of the tally before it is replaced.
+fn remove_dispute_vote(e: &mut Account<Escrow>, outcome: u8, seller_pct_bps: u16) {
+    match dispute_outcome_from_u8(outcome) {
+        DisputeOutcome::Refund => e.dispute_refund_votes = e.dispute_refund_votes.saturating_sub(1),
+        DisputeOutcome::Release => e.dispute_release_votes = e.dispute_release_votes.saturating_sub(1),
+        DisputeOutcome::Split => {
+            let n = e.dispute_split_votes as usize;
+            if let Some(pos) = e.dispute_split_bps[..n].iter().position(|b| *b == seller_pct_bps) {
+                e.dispute_split_bps.copy_within(pos + 1..n, pos);
+                e.dispute_split_bps[n - 1] = 0;
+                e.dispute_split_votes -= 1;
+            }
+        }
+    }
+}
+
+/// Median of the Split votes' seller percentages (mean of the middle two for an even count).
+fn median_bps(votes: &[u16]) -> u16 {
+    let mut sorted = [0u16; MAX_ARBITERS];
+    let n = votes.len();
+    sorted[..n].copy_from_slice(votes);
+    sorted[..n].sort_unstable();
+    if n % 2 == 1 {
+        sorted[n / 2]
+    } else {
+        ((sorted[n / 2 - 1] as u32 + sorted[n / 2] as u32) / 2) as u16
+    }
+}
+
 fn enter_transfer(e: &mut Account<Escrow>) -> Result<()> {
     require!(!e.in_transfer, EscrowError::Reentrancy);
     e.in_transfer = true;
@@ -1386,17 +3799,367 @@ fn transfer_from_vault<'info>(
     token::transfer(cpi_ctx, amount)
 }
 
-/// Count how many of the remaining accounts are signers AND are in the oracle set.
-fn count_quorum_votes(e: &Account<Escrow>, remaining: &[AccountInfo]) -> Result<usize> {
-    let mut votes = 0usize;
-    for ai in remaining.iter() {
-        if !ai.is_signer { continue; }
-        for i in 0..(e.oracles_len as usize) {
-            if e.oracles[i] != Pubkey::default() && e.oracles[i] == ai.key() {
-                votes += 1;
+/// Close an emptied vault-owned token account (e.g. a dispute bond vault); rent goes to `dest`.
+fn close_vault_token_account<'info>(
+    e: &Account<'info, Escrow>,
+    token_program: &Program<'info, Token>,
+    vault_authority: &UncheckedAccount<'info>,
+    account: &Account<'info, TokenAccount>,
+    dest: &UncheckedAccount<'info>,
+) -> Result<()> {
+    let escrow_key: Pubkey = e.key();
+    let bump = e.vault_bump;
+    let seeds_slice: [&[u8]; 3] = [b"vault", escrow_key.as_ref(), &[bump]];
+    let signer_seeds: [&[&[u8]]; 1] = [&seeds_slice];
+
+    let cpi_accounts = CloseAccount {
+        account: account.to_account_info(),
+        destination: dest.to_account_info(),
+        authority: vault_authority.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts)
+        .with_signer(&signer_seeds);
+    token::close_account(cpi_ctx)
+}
+
+/// Oracle votes attested in this transaction's Ed25519 program instructions, as a bitmap
+/// over oracle indices plus each vote's expiry. An attestation counts when its signer is
+/// in the oracle set, its message is `escrow | project_id | milestone_id | evidence_hash |
+/// expiry_ts | nonce` for this escrow and target, `expiry_ts` has not passed and `nonce` is
+/// the oracle's current `oracle_nonces` entry (bumped when it withdraws a vote). Signature, key
+/// and message must all live in the Ed25519 instruction itself (instruction indices = u16::MAX).
+fn collect_attestation_votes(
+    e: &Account<Escrow>,
+    instructions: &AccountInfo,
+    milestone_id: u16,
+    evidence_hash: &[u8; 32],
+    now: i64,
+) -> Result<(u8, [i64; MAX_ORACLES])> {
+    let mut expected = [0u8; ATTESTATION_MSG_LEN - 12];
+    expected[..32].copy_from_slice(e.key().as_ref());
+    expected[32..40].copy_from_slice(&e.project_id.to_le_bytes());
+    expected[40..42].copy_from_slice(&milestone_id.to_le_bytes());
+    expected[42..74].copy_from_slice(evidence_hash);
+
+    // Seen-bitmap over oracle indices: an oracle attesting more than once still counts once
+    let mut bits = 0u8;
+    let mut expiry = [0i64; MAX_ORACLES];
+    let mut idx = 0usize;
+    while let Ok(ix) = ix_sysvar::load_instruction_at_checked(idx, instructions) {
+        idx += 1;
+        if ix.program_id != ed25519_program::ID || ix.data.len() < 2 {
+            continue;
+        }
+        let data = &ix.data;
+        let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]) as usize;
+        // Layout: [num_signatures, padding, offsets (14 bytes each)...]
+        for n in 0..(data[0] as usize) {
+            let off = 2 + n * 14;
+            if data.len() < off + 14 {
                 break;
             }
+            let same_ix = u16::MAX as usize;
+            if read_u16(off + 2) != same_ix || read_u16(off + 6) != same_ix || read_u16(off + 12) != same_ix {
+                continue;
+            }
+            let (pk_at, msg_at, msg_len) = (read_u16(off + 4), read_u16(off + 8), read_u16(off + 10));
+            if msg_len != ATTESTATION_MSG_LEN || data.len() < pk_at + 32 || data.len() < msg_at + msg_len {
+                continue;
+            }
+            let msg = &data[msg_at..msg_at + msg_len];
+            let mut expiry_le = [0u8; 8];
+            expiry_le.copy_from_slice(&msg[74..82]);
+            let expires_ts = i64::from_le_bytes(expiry_le);
+            if msg[..74] != expected[..] || now > expires_ts {
+                continue;
+            }
+            let mut nonce_le = [0u8; 4];
+            nonce_le.copy_from_slice(&msg[82..86]);
+            let mut pk = [0u8; 32];
+            pk.copy_from_slice(&data[pk_at..pk_at + 32]);
+            if let Some(i) = oracle_index(e, &Pubkey::new_from_array(pk)) {
+                if u32::from_le_bytes(nonce_le) != e.oracle_nonces[i] {
+                    continue; // signed before the oracle's last withdrawal
+                }
+                bits |= 1 << i;
+                expiry[i] = expiry[i].max(expires_ts);
+            }
+        }
+    }
+    Ok((bits, expiry))
+}
+
+fn oracle_index(e: &Escrow, key: &Pubkey) -> Option<usize> {
+    e.oracles[..e.oracles_len as usize]
+        .iter()
+        .position(|o| *o != Pubkey::default() && o == key)
+}
+
+/// Number and total weight of live (unexpired) votes in a vote bitmap.
+fn count_quorum_votes(e: &Escrow, votes: u8, expiry: &[i64; MAX_ORACLES], now: i64) -> (u8, u32) {
+    (0..MAX_ORACLES)
+        .filter(|&i| votes & (1 << i) != 0 && now <= expiry[i])
+        .fold((0u8, 0u32), |(n, w), i| (n + 1, w + e.oracle_weights[i] as u32))
+}
+
+/// Merge new delivery votes and verify the delivery once live weight reaches `quorum_weight`.
+fn record_delivery_votes(
+    e: &mut Account<Escrow>,
+    bits: u8,
+    expiry: &[i64; MAX_ORACLES],
+    evidence_hash: &[u8; 32],
+    now: i64,
+) -> Result<()> {
+    require!(e.state == EscrowState::Open as u8 || e.state == EscrowState::PartiallyReleased as u8, EscrowError::BadState);
+    require!(e.verified_ts == 0, EscrowError::AlreadyVerified);
+
+    // Live votes must all refer to the same evidence
+    if count_quorum_votes(e, e.delivery_votes, &e.delivery_vote_expiry, now).0 == 0 {
+        e.delivery_votes = 0;
+        e.delivery_vote_hash = *evidence_hash;
+    }
+    require!(e.delivery_vote_hash == *evidence_hash, EscrowError::EvidenceMismatch);
+
+    for i in 0..MAX_ORACLES {
+        if bits & (1 << i) != 0 {
+            e.delivery_votes |= 1 << i;
+            e.delivery_vote_expiry[i] = expiry[i];
+        }
+    }
+    e.delivery_rejections &= !bits; // approving withdraws the oracle's own rejection
+
+    settle_delivery_votes(e, now);
+    Ok(())
+}
+
+/// Emit the live tally and verify the delivery if quorum is met and no veto stands.
+fn settle_delivery_votes(e: &mut Account<Escrow>, now: i64) {
+    let (votes, weight) = count_quorum_votes(e, e.delivery_votes, &e.delivery_vote_expiry, now);
+    let vetoed = is_vetoed(e, e.delivery_rejections);
+    emit!(OracleVotesRecorded {
+        project_id: e.project_id,
+        milestone_id: None,
+        live_votes: votes,
+        live_weight: weight,
+        quorum_weight: e.quorum_weight,
+        vetoed
+    });
+    if weight < e.quorum_weight || vetoed {
+        return;
+    }
+
+    e.last_evidence_hash = e.delivery_vote_hash;
+    if e.state == EscrowState::Open as u8 {
+        e.state = EscrowState::Verified as u8;
+    }
+    e.verified_ts = now;
+    e.delivery_votes = 0;
+
+    emit!(DeliveryVerified {
+        project_id: e.project_id,
+        quorum_votes: votes,
+        quorum_weight: weight,
+        when: now
+    });
+
+    // Warranty runs from verified delivery
+    start_warranty(e, now);
+}
+
+/// Drop milestone votes and rejections cast under a previous oracle set.
+fn sync_milestone_votes(e: &Escrow, m: &mut Account<Milestone>) {
+    if m.votes_oracle_set != e.oracle_set_version {
+        m.votes = 0;
+        m.vote_expiry = [0i64; MAX_ORACLES];
+        m.rejections = 0;
+        m.votes_oracle_set = e.oracle_set_version;
+        clear_milestone_escalation(e, m);
+    }
+}
+
+/// Merge new milestone votes and verify the milestone once live weight reaches `quorum_weight`.
+fn record_milestone_votes(
+    e: &mut Account<Escrow>,
+    m: &mut Account<Milestone>,
+    bits: u8,
+    expiry: &[i64; MAX_ORACLES],
+    now: i64,
+) -> Result<()> {
+    require!(
+        e.state == EscrowState::Open as u8
+            || e.state == EscrowState::Verified as u8
+            || e.state == EscrowState::PartiallyReleased as u8,
+        EscrowError::BadState
+    );
+    require!(!m.verified, EscrowError::AlreadyVerified);
+    sync_milestone_votes(e, m);
+    for i in 0..MAX_ORACLES {
+        if bits & (1 << i) != 0 {
+            m.votes |= 1 << i;
+            m.vote_expiry[i] = expiry[i];
+        }
+    }
+    m.rejections &= !bits; // approving withdraws the oracle's own rejection
+    clear_milestone_escalation(e, m);
+
+    settle_milestone_votes(e, m, now);
+    Ok(())
+}
+
+/// Emit the live tally and verify the milestone if quorum is met and no veto stands.
+fn settle_milestone_votes(e: &mut Account<Escrow>, m: &mut Account<Milestone>, now: i64) {
+    let (votes, weight) = count_quorum_votes(e, m.votes, &m.vote_expiry, now);
+    // An escalated milestone (rejections at quorum) stays blocked like a veto
+    let vetoed = is_vetoed(e, m.rejections) || rejection_weight(e, m.rejections) >= e.quorum_weight;
+    emit!(OracleVotesRecorded {
+        project_id: e.project_id,
+        milestone_id: Some(m.id),
+        live_votes: votes,
+        live_weight: weight,
+        quorum_weight: e.quorum_weight,
+        vetoed
+    });
+    if weight < e.quorum_weight || vetoed {
+        return;
+    }
+
+    m.verified = true;
+    m.verify_ts = now;
+    m.votes = 0;
+
+    if e.state == EscrowState::Open as u8 {
+        e.state = EscrowState::Verified as u8;
+    }
+
+    emit!(MilestoneVerified { project_id: e.project_id, id: m.id, when: now });
+}
+
+fn total_oracle_weight(e: &Escrow) -> u32 {
+    e.oracle_weights[..e.oracles_len as usize].iter().map(|w| *w as u32).sum()
+}
+
+/// Total weight of the oracles in a rejection bitmap (rejections do not expire).
+fn rejection_weight(e: &Escrow, rejections: u8) -> u32 {
+    (0..e.oracles_len as usize)
+        .filter(|&i| rejections & (1 << i) != 0)
+        .map(|i| e.oracle_weights[i] as u32)
+        .sum()
+}
+
+fn is_vetoed(e: &Escrow, rejections: u8) -> bool {
+    e.veto_weight > 0 && rejection_weight(e, rejections) >= e.veto_weight
+}
+
+/// Rejections crossing the verification quorum escalate. Delivery rejections move an
+/// active escrow into `Dispute`, remembering the state it left; milestone rejections only
+/// block that milestone (see `settle_milestone_votes`). Either party then opens the case
+/// with `open_dispute`.
+fn escalate_on_rejections(e: &mut Account<Escrow>, milestone: Option<&mut Milestone>, before: u32, rejected: u32) {
+    if before >= e.quorum_weight || rejected < e.quorum_weight {
+        return;
+    }
+    let milestone_id = match milestone {
+        Some(m) => {
+            m.escalated = true;
+            Some(m.id)
+        }
+        None => {
+            if e.state != EscrowState::Open as u8
+                && e.state != EscrowState::Verified as u8
+                && e.state != EscrowState::PartiallyReleased as u8
+            {
+                return;
+            }
+            e.escalated_from_state = e.state;
+            e.state = EscrowState::Dispute as u8;
+            None
         }
+    };
+    emit!(VerificationEscalated { project_id: e.project_id, milestone_id, rejection_weight: rejected });
+}
+
+/// Undo a delivery escalation once rejections fall back below quorum, unless a dispute
+/// has been opened on it in the meantime.
+fn restore_after_escalation(e: &mut Account<Escrow>) {
+    if e.escalated_from_state == 0
+        || e.state != EscrowState::Dispute as u8
+        || e.dispute_open
+        || rejection_weight(e, e.delivery_rejections) >= e.quorum_weight
+    {
+        return;
     }
-    Ok(votes)
+    e.state = e.escalated_from_state;
+    e.escalated_from_state = 0;
+    emit!(EscalationCleared { project_id: e.project_id, milestone_id: None, state: e.state });
+}
+
+/// Drop a milestone's escalation once its rejections fall back below quorum.
+fn clear_milestone_escalation(e: &Escrow, m: &mut Milestone) {
+    if !m.escalated || rejection_weight(e, m.rejections) >= e.quorum_weight {
+        return;
+    }
+    m.escalated = false;
+    emit!(EscalationCleared { project_id: e.project_id, milestone_id: Some(m.id), state: e.state });
+}
+
+/// Expire and refund one keeper-supplied escrow group.
+/// Returns `None` (instead of failing the batch) when the group is not eligible.
+fn try_expire_escrow<'info>(
+    token_program: &Program<'info, Token>,
+    group: &'info [AccountInfo<'info>],
+    now: i64,
+) -> Result<Option<(u64, u64)>> {
+    let (escrow_ai, vault_auth_ai, vault_ai, buyer_ai) = (&group[0], &group[1], &group[2], &group[3]);
+    if !escrow_ai.is_writable || !vault_ai.is_writable || !buyer_ai.is_writable {
+        return Ok(None);
+    }
+
+    let mut e: Account<'info, Escrow> = match Account::try_from(escrow_ai) {
+        Ok(e) => e,
+        Err(_) => return Ok(None),
+    };
+    if e.state != EscrowState::Open as u8 || e.in_transfer || e.dispute_open || e.verify_by_ts == 0 || now <= e.verify_by_ts {
+        return Ok(None);
+    }
+
+    // Vault authority must be this escrow's PDA
+    let escrow_key = e.key();
+    match Pubkey::create_program_address(&[b"vault", escrow_key.as_ref(), &[e.vault_bump]], &crate::ID) {
+        Ok(expected) if expected == vault_auth_ai.key() => {}
+        _ => return Ok(None),
+    }
+
+    let vault_ata: Account<'info, TokenAccount> = match Account::try_from(vault_ai) {
+        Ok(a) => a,
+        Err(_) => return Ok(None),
+    };
+    let buyer_ata: Account<'info, TokenAccount> = match Account::try_from(buyer_ai) {
+        Ok(a) => a,
+        Err(_) => return Ok(None),
+    };
+    // Same check as the `associated_token::` constraints: only the canonical vault ATA
+    if vault_ai.key() != get_associated_token_address(&vault_auth_ai.key(), &e.mint)
+        || vault_ata.owner != vault_auth_ai.key()
+        || vault_ata.mint != e.mint
+    {
+        return Ok(None);
+    }
+    if buyer_ata.owner != e.buyer || buyer_ata.mint != e.mint {
+        return Ok(None);
+    }
+
+    let refund_amount = vault_ata.amount;
+    if refund_amount < e.amount {
+        return Ok(None);
+    }
+
+    let vault_authority = UncheckedAccount::try_from(vault_auth_ai);
+    transfer_from_vault(&e, token_program, &vault_authority, &vault_ata, &buyer_ata, refund_amount)?;
+
+    e.state = EscrowState::Refunded as u8;
+    e.released_ts = now;
+    // Persist: remaining accounts are not serialized back automatically
+    e.exit(&crate::ID)?;
+
+    Ok(Some((e.project_id, refund_amount)))
 }
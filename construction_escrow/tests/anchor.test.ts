@@ -63,6 +63,38 @@ describe("construction_escrow end-to-end", () => {
   function pdaVaultAuthority(escrow: web3.PublicKey) {
     return web3.PublicKey.findProgramAddressSync([Buffer.from("vault"), escrow.toBuffer()], PROGRAM_ID);
   }
+  function pdaMilestone(escrow: web3.PublicKey, id: number) {
+    const idBuf = Buffer.alloc(2);
+    idBuf.writeUInt16BE(id);
+    return web3.PublicKey.findProgramAddressSync([Buffer.from("milestone"), escrow.toBuffer(), idBuf], PROGRAM_ID);
+  }
+
+  // Oracle sign-off message (must match ATTESTATION_MSG_LEN layout in lib.rs):
+  // escrow | project_id u64 LE | milestone_id u16 LE | evidence_hash | expiry_ts i64 LE | nonce u32 LE
+  const DELIVERY_ATTESTATION_ID = 0xffff;
+  function attestationIx(
+    oracle: web3.Keypair,
+    escrow: web3.PublicKey,
+    projectIdBN: BN,
+    milestoneId: number,
+    evidenceHash: number[],
+    expiryTs: number,
+    nonce = 0 // escrow.oracleNonces[i]; bumped each time the oracle withdraws a vote
+  ) {
+    const idBuf = Buffer.alloc(2);
+    idBuf.writeUInt16LE(milestoneId);
+    const nonceBuf = Buffer.alloc(4);
+    nonceBuf.writeUInt32LE(nonce);
+    const message = Buffer.concat([
+      escrow.toBuffer(),
+      Buffer.from(projectIdBN.toArray("le", 8)),
+      idBuf,
+      Buffer.from(evidenceHash),
+      Buffer.from(new BN(expiryTs).toArray("le", 8)),
+      nonceBuf,
+    ]);
+    return web3.Ed25519Program.createInstructionWithPrivateKey({ privateKey: oracle.secretKey, message });
+  }
 
   async function getLogs(sig: string) {
     const tx = await pg.connection.getTransaction(sig, {
@@ -72,6 +104,294 @@ describe("construction_escrow end-to-end", () => {
     return tx?.meta?.logMessages || [];
   }
 
+  // Decode the program's `name` events from a confirmed transaction's logs
+  async function eventsOf(sig: string, name: string) {
+    const prefix = "Program data: ";
+    return (await getLogs(sig))
+      .filter((l) => l.startsWith(prefix))
+      .map((l) => pg.program.coder.events.decode(l.slice(prefix.length)))
+      .filter((ev) => ev && ev.name.toLowerCase() === name.toLowerCase())
+      .map((ev) => ev.data as any);
+  }
+
+  // Run `fn`, expect it to fail with the given Anchor error code
+  async function expectError(label: string, code: string, fn: () => Promise<any>) {
+    let failed = false;
+    try {
+      await fn();
+    } catch (e: any) {
+      failed = true;
+      const got = e?.error?.errorCode?.code || `${e}`;
+      console.log(`[${label}] expected failure:`, got);
+      assert.ok(`${got}`.includes(code), `${label}: expected ${code}, got ${got}`);
+    }
+    assert.ok(failed, `${label} should fail with ${code}`);
+  }
+
+  async function tokenBalance(ata: web3.PublicKey) {
+    return Number((await splToken.getAccount(pg.connection, ata)).amount);
+  }
+
+  async function ensureAta(mint: web3.PublicKey, owner: web3.PublicKey) {
+    const ata = await splToken.getAssociatedTokenAddress(mint, owner, true);
+    if (!(await pg.connection.getAccountInfo(ata))) {
+      const ix = splToken.createAssociatedTokenAccountInstruction(WALLET, ata, owner, mint);
+      await web3.sendAndConfirmTransaction(pg.connection, new web3.Transaction().add(ix), [PAYER]);
+    }
+    return ata;
+  }
+
+  // Config is a singleton: reuse it if an earlier run already created it
+  async function ensureConfig() {
+    const [configPda] = pdaConfig();
+    if (!(await pg.connection.getAccountInfo(configPda))) {
+      await pg.program.methods
+        .initConfig(100, 50, 500, new BN(0), new BN(0), new BN(3650), 1)
+        .accounts({
+          authority: WALLET,
+          treasury: web3.Keypair.generate().publicKey,
+          insuranceTreasury: web3.Keypair.generate().publicKey,
+          arbiter: WALLET,
+          config: configPda,
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .signers([PAYER])
+        .rpc();
+    }
+    return configPda;
+  }
+
+  // ---- focused-case fixture: fresh mint, seller and escrow per case ---------
+  let nextProjectId = Date.now();
+
+  async function setupEscrow(opts: {
+    amount?: number;
+    oracles?: web3.Keypair[];
+    weights?: number[];
+    quorumWeight?: number;
+    warrantyDays?: number;
+  } = {}) {
+    const amount = opts.amount ?? 100_000_000;
+    const oracles = opts.oracles ?? [web3.Keypair.generate()];
+    const weights = opts.weights ?? oracles.map(() => 1);
+    const seller = web3.Keypair.generate();
+    await airdrop(seller.publicKey);
+
+    const mintKp = web3.Keypair.generate();
+    const mintRent = await splToken.getMinimumBalanceForRentExemptMint(pg.connection);
+    await web3.sendAndConfirmTransaction(
+      pg.connection,
+      new web3.Transaction().add(
+        web3.SystemProgram.createAccount({
+          fromPubkey: WALLET,
+          newAccountPubkey: mintKp.publicKey,
+          lamports: mintRent,
+          space: splToken.MintLayout.span,
+          programId: splToken.TOKEN_PROGRAM_ID,
+        }),
+        splToken.createInitializeMintInstruction(mintKp.publicKey, 6, WALLET, WALLET)
+      ),
+      [PAYER, mintKp]
+    );
+    const mint = mintKp.publicKey;
+
+    const config = await ensureConfig();
+    const cfg = await pg.program.account.config.fetch(config);
+    const buyerAta = await ensureAta(mint, WALLET);
+    const sellerAta = await ensureAta(mint, seller.publicKey);
+    const treasuryAta = await ensureAta(mint, cfg.treasury);
+    const insuranceAta = await ensureAta(mint, cfg.insuranceTreasury);
+    await web3.sendAndConfirmTransaction(
+      pg.connection,
+      new web3.Transaction().add(splToken.createMintToInstruction(mint, buyerAta, WALLET, 1_000_000_000)),
+      [PAYER]
+    );
+
+    const projectId = u64(nextProjectId++);
+    const [escrow] = pdaEscrow(projectId, WALLET, seller.publicKey, mint);
+    const [projectIndex] = pdaProjectIndex(projectId);
+    const [vaultAuth] = pdaVaultAuthority(escrow);
+    const vaultAta = await splToken.getAssociatedTokenAddress(mint, vaultAuth, true);
+
+    await pg.program.methods
+      .createEscrow(
+        projectId,
+        u64(amount),
+        u64(Date.now()),
+        oracles.map((o) => o.publicKey),
+        weights,
+        opts.quorumWeight ?? 1,
+        u64(0),
+        false,
+        new BN(opts.warrantyDays ?? 0)
+      )
+      .accounts({
+        buyer: WALLET,
+        seller: seller.publicKey,
+        mint,
+        buyerAta,
+        escrow,
+        projectIndex,
+        vaultAuthority: vaultAuth,
+        vaultAta,
+        config,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .signers([PAYER])
+      .rpc();
+
+    return {
+      projectId, amount, oracles, seller, mint, config, escrow, vaultAuth, vaultAta,
+      buyerAta, sellerAta, treasuryAta, insuranceAta,
+    };
+  }
+  type Fixture = Awaited<ReturnType<typeof setupEscrow>>;
+
+  async function setDeadlines(fx: Fixture, verifyByTs: number, deliverByTs: number) {
+    await pg.program.methods
+      .proposeDeadlines(new BN(verifyByTs), new BN(deliverByTs))
+      .accounts({ actor: WALLET, escrow: fx.escrow })
+      .signers([PAYER])
+      .rpc();
+    await pg.program.methods
+      .acceptDeadlines()
+      .accounts({ actor: fx.seller.publicKey, escrow: fx.escrow })
+      .signers([fx.seller])
+      .rpc();
+  }
+
+  // ---- dispute helpers (seeds use the 2-byte big-endian dispute number) ----
+  function disputeNoBuf(no: number) {
+    const b = Buffer.alloc(2);
+    b.writeUInt16BE(no);
+    return b;
+  }
+  function pdaDispute(escrow: web3.PublicKey, no: number) {
+    return web3.PublicKey.findProgramAddressSync([Buffer.from("dispute"), escrow.toBuffer(), disputeNoBuf(no)], PROGRAM_ID);
+  }
+  function pdaBondVault(escrow: web3.PublicKey, no: number) {
+    return web3.PublicKey.findProgramAddressSync([Buffer.from("bond"), escrow.toBuffer(), disputeNoBuf(no)], PROGRAM_ID);
+  }
+  function pdaAppealBondVault(escrow: web3.PublicKey, no: number) {
+    return web3.PublicKey.findProgramAddressSync([Buffer.from("appeal_bond"), escrow.toBuffer(), disputeNoBuf(no)], PROGRAM_ID);
+  }
+  function pdaDisputeVote(escrow: web3.PublicKey, no: number, arbiter: web3.PublicKey) {
+    return web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("dispute_vote"), escrow.toBuffer(), disputeNoBuf(no), arbiter.toBuffer()],
+      PROGRAM_ID
+    );
+  }
+
+  // Config dispute terms are global: each dispute case starts from no bond, fee or appeals
+  async function resetDisputeTerms() {
+    const [config] = pdaConfig();
+    await pg.program.methods.setDisputeResponseWindow(new BN(0)).accounts({ config, authority: WALLET }).signers([PAYER]).rpc();
+    await pg.program.methods.setDisputeBond(u64(0), { counterparty: {} }).accounts({ config, authority: WALLET }).signers([PAYER]).rpc();
+    await pg.program.methods.setArbiterFee(0, { loser: {} }).accounts({ config, authority: WALLET }).signers([PAYER]).rpc();
+    await pg.program.methods
+      .setAppealTerms(new BN(0), web3.PublicKey.default, u64(0))
+      .accounts({ config, authority: WALLET })
+      .signers([PAYER])
+      .rpc();
+  }
+
+  async function newPanel(fx: Fixture, size: number, threshold: number) {
+    const panel = Array.from({ length: size }, () => web3.Keypair.generate());
+    for (const a of panel) await airdrop(a.publicKey);
+    await pg.program.methods
+      .setEscrowArbiters(panel.map((a) => a.publicKey), threshold)
+      .accounts({ buyer: WALLET, seller: fx.seller.publicKey, escrow: fx.escrow })
+      .signers([PAYER, fx.seller])
+      .rpc();
+    return panel;
+  }
+
+  // Opens the next dispute; `claimant` is PAYER (buyer) or fx.seller
+  async function openDispute(fx: Fixture, claimant: web3.Keypair, milestone: web3.PublicKey | null = null) {
+    const no = (await pg.program.account.escrow.fetch(fx.escrow)).disputesCount + 1;
+    const [dispute] = pdaDispute(fx.escrow, no);
+    const [bondVault] = pdaBondVault(fx.escrow, no);
+    const actorAta = claimant.publicKey.equals(WALLET) ? fx.buyerAta : fx.sellerAta;
+    await logOnErr("open_dispute", () =>
+      pg.program.methods
+        .openDispute(1, Array(32).fill(9))
+        .accounts({
+          actor: claimant.publicKey,
+          config: fx.config,
+          escrow: fx.escrow,
+          mint: fx.mint,
+          dispute,
+          milestone,
+          vaultAuthority: fx.vaultAuth,
+          vaultAta: fx.vaultAta,
+          bondVault,
+          actorAta,
+          tokenProgram: splToken.TOKEN_PROGRAM_ID,
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .signers([claimant])
+        .rpc()
+    );
+    return { no, dispute, bondVault, claimant: claimant.publicKey, milestone };
+  }
+  type Dispute = Awaited<ReturnType<typeof openDispute>>;
+
+  async function submitEvidence(fx: Fixture, d: Dispute, by: web3.Keypair, tag: number) {
+    await pg.program.methods
+      .submitDisputeEvidence(Array(32).fill(tag), Buffer.from(`ipfs://evidence-${tag}`))
+      .accounts({ actor: by.publicKey, escrow: fx.escrow, dispute: d.dispute })
+      .signers([by])
+      .rpc();
+  }
+
+  async function castVote(fx: Fixture, d: Dispute, arbiter: web3.Keypair, outcome: any, sellerPctBps = 0) {
+    await pg.program.methods
+      .castDisputeVote(outcome, sellerPctBps)
+      .accounts({
+        config: fx.config,
+        arbiter: arbiter.publicKey,
+        escrow: fx.escrow,
+        dispute: d.dispute,
+        vote: pdaDisputeVote(fx.escrow, d.no, arbiter.publicKey)[0],
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([arbiter])
+      .rpc();
+  }
+
+  async function executeRuling(fx: Fixture, d: Dispute, extra: Record<string, any> = {}) {
+    const rec = await pg.program.account.disputeRecord.fetch(d.dispute);
+    const arbiterAta = await ensureAta(fx.mint, rec.ruledBy); // fee goes to the arbiter that ruled
+    await logOnErr("execute_ruling", () =>
+      pg.program.methods
+        .executeRuling()
+        .accounts({
+          caller: WALLET,
+          config: fx.config,
+          escrow: fx.escrow,
+          dispute: d.dispute,
+          milestone: d.milestone,
+          vaultAuthority: fx.vaultAuth,
+          vaultAta: fx.vaultAta,
+          bondVault: d.bondVault,
+          claimant: d.claimant,
+          appealBondVault: null,
+          appellant: null,
+          buyerAta: fx.buyerAta,
+          sellerAta: fx.sellerAta,
+          treasuryAta: fx.treasuryAta,
+          insuranceAta: fx.insuranceAta,
+          arbiterAta,
+          tokenProgram: splToken.TOKEN_PROGRAM_ID,
+          ...extra,
+        })
+        .rpc()
+    );
+  }
+
   // ---- test body -----------------------------------------------------------
   it("happy path: config → create_escrow → verify → milestone → release → final release", async () => {
     // ----- bootstrap SPL mint and ATAs -------------------------------------
@@ -79,7 +399,6 @@ describe("construction_escrow end-to-end", () => {
     const sellerKp = web3.Keypair.generate();
     const treasuryKp = web3.Keypair.generate();
     const insuranceKp = web3.Keypair.generate();
-    const arbiterKp = web3.Keypair.generate();
 
     // Two oracles for quorum=1 (M-of-N)
     const oracle1 = web3.Keypair.generate();
@@ -90,7 +409,6 @@ describe("construction_escrow end-to-end", () => {
     await airdrop(sellerKp.publicKey);
     await airdrop(treasuryKp.publicKey);
     await airdrop(insuranceKp.publicKey);
-    await airdrop(arbiterKp.publicKey);
     await airdrop(oracle1.publicKey);
     await airdrop(oracle2.publicKey);
 
@@ -124,8 +442,8 @@ describe("construction_escrow end-to-end", () => {
       mintKp.publicKey, WALLET, false, splToken.TOKEN_PROGRAM_ID, splToken.ASSOCIATED_TOKEN_PROGRAM_ID
     );
     const sellerAta = await splToken.getAssociatedTokenAddress(mintKp.publicKey, sellerKp.publicKey);
-    const treasuryAta = await splToken.getAssociatedTokenAddress(mintKp.publicKey, treasuryKp.publicKey);
-    const insuranceAta = await splToken.getAssociatedTokenAddress(mintKp.publicKey, insuranceKp.publicKey);
+    let treasuryAta = await splToken.getAssociatedTokenAddress(mintKp.publicKey, treasuryKp.publicKey);
+    let insuranceAta = await splToken.getAssociatedTokenAddress(mintKp.publicKey, insuranceKp.publicKey);
 
     // Ensure ATAs exist
     const createBuyerAtaIx = splToken.createAssociatedTokenAccountInstruction(
@@ -165,7 +483,7 @@ describe("construction_escrow end-to-end", () => {
     }
 
     // ----- derive PDAs that match lib.rs -----------------------------------
-    const projectId = u64(1234);
+    const projectId = u64(nextProjectId++); // project_index PDAs are global: fresh id per run
     const [configPda] = pdaConfig();
     const [projectIndexPda] = pdaProjectIndex(projectId);
     const [escrowPda] = pdaEscrow(projectId, WALLET, sellerKp.publicKey, mintKp.publicKey);
@@ -191,6 +509,8 @@ describe("construction_escrow end-to-end", () => {
     const insuranceBps = 50;  // 0.5%
     const retentionBps = 500; // 5%
     const warrantyDays = new BN(0); // allow immediate retention release for test
+    const minWarrantyDays = new BN(0);
+    const maxWarrantyDays = new BN(3650);
     const quorumM = 1;
 
     await logOnErr("init_config", async () => {
@@ -200,12 +520,12 @@ describe("construction_escrow end-to-end", () => {
         return;
       }
       const sig = await pg.program.methods
-        .initConfig(feeBps, insuranceBps, retentionBps, warrantyDays, quorumM)
+        .initConfig(feeBps, insuranceBps, retentionBps, warrantyDays, minWarrantyDays, maxWarrantyDays, quorumM)
         .accounts({
           authority: WALLET,
           treasury: treasuryKp.publicKey,
           insuranceTreasury: insuranceKp.publicKey,
-          arbiter: arbiterKp.publicKey,
+          arbiter: WALLET, // the focused cases sign as Config.arbiter
           config: configPda,
           systemProgram: web3.SystemProgram.programId,
         })
@@ -215,10 +535,20 @@ describe("construction_escrow end-to-end", () => {
       console.log("init_config logs:", await getLogs(sig));
     });
 
+    // On reruns init_config was skipped: payouts must go to the treasuries the stored Config
+    // names (release contexts check the ATA owners), not to this run's fresh keypairs
+    {
+      const cfg = await pg.program.account.config.fetch(configPda);
+      treasuryAta = await ensureAta(mintKp.publicKey, cfg.treasury);
+      insuranceAta = await ensureAta(mintKp.publicKey, cfg.insuranceTreasury);
+    }
+
     // ----- create_escrow ---------------------------------------------------
     const amount = u64(100_000_000); // 100 tokens (6 decimals)
     const ixNonce = u64(Date.now()); // idempotency key
     const oracles: web3.PublicKey[] = [oracle1.publicKey, oracle2.publicKey];
+    const oracleWeights = [1, 1]; // e.g. raise the engineer's weight above the foreman's
+    const quorumWeight = 1;
     const priceSnapshot = u64(10_000_000); // 10.000000 USD for example
     const nftEnabled = false;
 
@@ -229,9 +559,11 @@ describe("construction_escrow end-to-end", () => {
           amount,
           ixNonce,
           oracles,
-          quorumM,
+          oracleWeights,
+          quorumWeight,
           priceSnapshot,
-          nftEnabled
+          nftEnabled,
+          warrantyDays
         )
         .accounts({
           buyer: WALLET,
@@ -267,34 +599,58 @@ describe("construction_escrow end-to-end", () => {
     assert.equal(escrowAccAfterCreate.amount.toString(), amount.toString());
     assert.equal(escrowAccAfterCreate.state, 1 /* Open */);
 
-    // ----- set deadlines (verify_by_ts + deliver_by_ts) --------------------
+    // ----- deadlines: buyer proposes, seller countersigns -------------------
     const now = Math.floor(Date.now() / 1000);
-    await logOnErr("set_deadlines", async () => {
+    await logOnErr("propose_deadlines", async () => {
       const sig = await pg.program.methods
-        .setDeadlines(new BN(now + 60), new BN(now + 120))
+        .proposeDeadlines(new BN(now + 60), new BN(now + 120))
         .accounts({
           actor: WALLET,
           escrow: escrowPda,
         })
         .signers([PAYER])
         .rpc();
-      console.log("set_deadlines sig:", sig);
-      console.log("set-deadlines logs:", await getLogs(sig));
+      console.log("propose_deadlines sig:", sig);
+      console.log("propose_deadlines logs:", await getLogs(sig));
+    });
+    await logOnErr("accept_deadlines", async () => {
+      const sig = await pg.program.methods
+        .acceptDeadlines()
+        .accounts({
+          actor: sellerKp.publicKey,
+          escrow: escrowPda,
+        })
+        .signers([sellerKp])
+        .rpc();
+      console.log("accept_deadlines sig:", sig);
+      console.log("accept_deadlines logs:", await getLogs(sig));
     });
 
+    // Validate deadlines applied only after countersign
+    {
+      const e = await pg.program.account.escrow.fetch(escrowPda);
+      assert.equal(e.verifyByTs.toNumber(), now + 60);
+      assert.equal(e.deliverByTs.toNumber(), now + 120);
+      assert.equal(e.deadlinesProposedBy.toBase58(), web3.PublicKey.default.toBase58());
+    }
+
     // ----- add a milestone --------------------------------------------------
     const milestoneAmount = u64(40_000_000); // 40 tokens
     const evHashU8 = new Uint8Array(32); // zeroed test hash
     const evHashNumArr = Array.from(evHashU8); // convert to number[]
+    const [milestonePda] = pdaMilestone(escrowPda, 0);
 
     await logOnErr("add_milestone", async () => {
       const sig = await pg.program.methods
-        .addMilestone(milestoneAmount, evHashNumArr)
+        .addMilestone(milestoneAmount, 0, evHashNumArr, new BN(0), { none: {} }, 0, 0)
         .accounts({
-          actor: WALLET,
+          buyer: WALLET,
+          seller: sellerKp.publicKey,
           escrow: escrowPda,
+          milestone: milestonePda,
+          systemProgram: web3.SystemProgram.programId,
         })
-        .signers([PAYER])
+        .signers([PAYER, sellerKp])
         .rpc();
       console.log("add_milestone sig:", sig);
       console.log("add_milestone logs:", await getLogs(sig));
@@ -303,18 +659,20 @@ describe("construction_escrow end-to-end", () => {
     // Validate milestone added
     {
       const e = await pg.program.account.escrow.fetch(escrowPda);
-      assert.equal(e.milestonesLen, 1);
-      assert.equal(e.milestones[0].amount.toString(), milestoneAmount.toString());
-      assert.equal(e.milestones[0].verified, false);
+      assert.equal(e.milestonesCount, 1);
+      assert.equal(e.milestonesTotal.toString(), milestoneAmount.toString());
+      const m = await pg.program.account.milestone.fetch(milestonePda);
+      assert.equal(m.amount.toString(), milestoneAmount.toString());
+      assert.equal(m.verified, false);
     }
 
-    // ----- verify milestone with quorum (1 of 2 oracles) -------------------
+    // ----- verify milestone with quorum (1 of 2 oracles, Ed25519 attestation) -
+    const attestationExpiry = Math.floor(Date.now() / 1000) + 3600;
     await logOnErr("verify_milestone", async () => {
       const sig = await pg.program.methods
         .verifyMilestone(0)
-        .accounts({ escrow: escrowPda })
-        .remainingAccounts([{ pubkey: oracle1.publicKey, isSigner: true, isWritable: false }])
-        .signers([oracle1])
+        .accounts({ escrow: escrowPda, milestone: milestonePda, instructions: web3.SYSVAR_INSTRUCTIONS_PUBKEY })
+        .preInstructions([attestationIx(oracle1, escrowPda, projectId, 0, evHashNumArr, attestationExpiry)])
         .rpc();
       console.log("verify_milestone sig:", sig);
       console.log("verify_milestone logs:", await getLogs(sig));
@@ -323,7 +681,8 @@ describe("construction_escrow end-to-end", () => {
     // Validate milestone verified
     {
       const e = await pg.program.account.escrow.fetch(escrowPda);
-      assert.equal(e.milestones[0].verified, true);
+      const m = await pg.program.account.milestone.fetch(milestonePda);
+      assert.equal(m.verified, true);
       assert.ok(e.state === 2 || e.state === 3); // Verified or PartiallyReleased
     }
 
@@ -333,6 +692,8 @@ describe("construction_escrow end-to-end", () => {
         .releaseForMilestone(0)
         .accounts({
           escrow: escrowPda,
+          config: configPda,
+          milestone: milestonePda,
           vaultAuthority: vaultAuthPda,
           vaultAta,
           sellerAta,
@@ -346,16 +707,18 @@ describe("construction_escrow end-to-end", () => {
       console.log("release_for_milestone logs:", await getLogs(sig));
     });
 
-    // Validate balances roughly (40 tokens - fees)
+    // Validate balances roughly (40 tokens - retention - fees)
     {
       const sellerAcc = await splToken.getAccount(pg.connection, sellerAta);
       const treasuryAcc = await splToken.getAccount(pg.connection, treasuryAta);
       const insuranceAcc = await splToken.getAccount(pg.connection, insuranceAta);
       const vaultAcc = await splToken.getAccount(pg.connection, vaultAta);
 
-      const fee = Math.floor(Number(milestoneAmount) * feeBps / 10_000);
-      const ins = Math.floor(Number(milestoneAmount) * insuranceBps / 10_000);
-      const sellerRecv = Number(milestoneAmount) - fee - ins;
+      const retCut = Math.floor(Number(milestoneAmount) * retentionBps / 10_000);
+      const paidOut = Number(milestoneAmount) - retCut;
+      const fee = Math.floor(paidOut * feeBps / 10_000);
+      const ins = Math.floor(paidOut * insuranceBps / 10_000);
+      const sellerRecv = paidOut - fee - ins;
 
       console.log("post milestone release balances:", {
         seller: Number(sellerAcc.amount),
@@ -367,16 +730,20 @@ describe("construction_escrow end-to-end", () => {
       assert.equal(Number(treasuryAcc.amount), fee);
       assert.equal(Number(insuranceAcc.amount), ins);
       assert.equal(Number(sellerAcc.amount), sellerRecv);
-      assert.ok(Number(vaultAcc.amount) <= Number(amount) - Number(milestoneAmount) + 1);
+      assert.equal(Number(vaultAcc.amount), Number(amount) - paidOut);
+
+      const e = await pg.program.account.escrow.fetch(escrowPda);
+      assert.equal(e.retentionHeld.toNumber(), retCut);
     }
 
     // ----- verify_delivery (overall) ---------------------------------------
     await logOnErr("verify_delivery", async () => {
       const sig = await pg.program.methods
-        .verifyDelivery(projectId)
-        .accounts({ escrow: escrowPda })
-        .remainingAccounts([{ pubkey: oracle1.publicKey, isSigner: true, isWritable: false }])
-        .signers([oracle1])
+        .verifyDelivery(projectId, evHashNumArr)
+        .accounts({ escrow: escrowPda, instructions: web3.SYSVAR_INSTRUCTIONS_PUBKEY })
+        .preInstructions([
+          attestationIx(oracle1, escrowPda, projectId, DELIVERY_ATTESTATION_ID, evHashNumArr, attestationExpiry),
+        ])
         .rpc();
       console.log("verify_delivery sig:", sig);
       console.log("verify_delivery logs:", await getLogs(sig));
@@ -388,6 +755,7 @@ describe("construction_escrow end-to-end", () => {
         .releasePayment()
         .accounts({
           escrow: escrowPda,
+          config: configPda,
           vaultAuthority: vaultAuthPda,
           vaultAta,
           sellerAta,
@@ -417,6 +785,7 @@ describe("construction_escrow end-to-end", () => {
         .releaseRetention()
         .accounts({
           escrow: escrowPda,
+          config: configPda,
           vaultAuthority: vaultAuthPda,
           vaultAta,
           sellerAta,
@@ -485,7 +854,7 @@ describe("construction_escrow end-to-end", () => {
       [PAYER]
     );
 
-    const projectId = u64(9999);
+    const projectId = u64(nextProjectId++);
     const [configPda] = pdaConfig();
     const [escrowPda] = pdaEscrow(projectId, WALLET, sellerKp.publicKey, mint.publicKey);
     const [vaultAuth] = pdaVaultAuthority(escrowPda);
@@ -496,8 +865,8 @@ describe("construction_escrow end-to-end", () => {
     const insuranceKp = web3.Keypair.generate();
     await airdrop(treasuryKp.publicKey);
     await airdrop(insuranceKp.publicKey);
-    const treasuryAta = await splToken.getAssociatedTokenAddress(mint.publicKey, treasuryKp.publicKey);
-    const insuranceAta = await splToken.getAssociatedTokenAddress(mint.publicKey, insuranceKp.publicKey);
+    let treasuryAta = await splToken.getAssociatedTokenAddress(mint.publicKey, treasuryKp.publicKey);
+    let insuranceAta = await splToken.getAssociatedTokenAddress(mint.publicKey, insuranceKp.publicKey);
     try {
       await web3.sendAndConfirmTransaction(
         pg.connection,
@@ -515,7 +884,7 @@ describe("construction_escrow end-to-end", () => {
     const cfgAcc = await pg.connection.getAccountInfo(configPda);
     if (!cfgAcc) {
       const sig = await pg.program.methods
-        .initConfig(100, 50, 500, new BN(0), 1)
+        .initConfig(100, 50, 500, new BN(0), new BN(0), new BN(3650), 1)
         .accounts({
           authority: WALLET,
           treasury: treasuryKp.publicKey,
@@ -531,7 +900,7 @@ describe("construction_escrow end-to-end", () => {
 
     // Create escrow with no verification yet
     await pg.program.methods
-      .createEscrow(projectId, u64(200_000_000), u64(Date.now()), [], 1, u64(0), false)
+      .createEscrow(projectId, u64(200_000_000), u64(Date.now()), [web3.Keypair.generate().publicKey], [1], 1, u64(0), false, new BN(0))
       .accounts({
         buyer: WALLET,
         seller: sellerKp.publicKey,
@@ -550,6 +919,13 @@ describe("construction_escrow end-to-end", () => {
       .signers([PAYER])
       .rpc();
 
+    // Payout ATAs must belong to the Config treasuries, which may predate this run
+    {
+      const cfg = await pg.program.account.config.fetch(configPda);
+      treasuryAta = await ensureAta(mint.publicKey, cfg.treasury);
+      insuranceAta = await ensureAta(mint.publicKey, cfg.insuranceTreasury);
+    }
+
     // Try (and fail) to release_payment before verification
     let failed = false;
     try {
@@ -557,6 +933,7 @@ describe("construction_escrow end-to-end", () => {
         .releasePayment()
         .accounts({
           escrow: escrowPda,
+          config: configPda,
           vaultAuthority: vaultAuth,
           vaultAta,
           sellerAta,
@@ -569,6 +946,7 @@ describe("construction_escrow end-to-end", () => {
     } catch (e:any) {
       failed = true;
       console.log("Expected failure (release before verify). Error:", e.error ? e.error : e.toString());
+      assert.equal(e?.error?.errorCode?.code, "BadState", "fails on state, not on payout accounts");
       if (e?.signature || e?.txid) {
         const logs = await getLogs(e.signature || e.txid);
         console.log("Failure logs:", logs);
@@ -576,4 +954,1186 @@ describe("construction_escrow end-to-end", () => {
     }
     assert.ok(failed, "release_payment should fail before verification");
   });
+
+  it("process_timeouts: refunds expired escrows and skips ineligible groups", async () => {
+    const expired = await setupEscrow();
+    const wrongBuyerAta = await setupEscrow(); // expired, but the group passes the seller ATA
+    const strayVault = await setupEscrow(); // expired, but the group passes a non-ATA vault account
+    const live = await setupEscrow(); // no verify_by_ts: never expires
+    const past = Math.floor(Date.now() / 1000) - 3600;
+    await setDeadlines(expired, past, past);
+    await setDeadlines(wrongBuyerAta, past, past);
+    await setDeadlines(strayVault, past, past);
+
+    // A funded token account owned by the vault PDA with the right mint, but not its ATA
+    const stray = await splToken.createAccount(pg.connection, PAYER, strayVault.mint, strayVault.vaultAuth, web3.Keypair.generate());
+    await splToken.mintTo(pg.connection, PAYER, strayVault.mint, stray, PAYER, strayVault.amount);
+
+    const group = (fx: Fixture, buyerAta: web3.PublicKey, vaultAta = fx.vaultAta) => [
+      { pubkey: fx.escrow, isSigner: false, isWritable: true },
+      { pubkey: fx.vaultAuth, isSigner: false, isWritable: false },
+      { pubkey: vaultAta, isSigner: false, isWritable: true },
+      { pubkey: buyerAta, isSigner: false, isWritable: true },
+    ];
+    const buyerBefore = await tokenBalance(expired.buyerAta);
+
+    await logOnErr("process_timeouts", async () => {
+      const sig = await pg.program.methods
+        .processTimeouts(5)
+        .accounts({ caller: WALLET, tokenProgram: splToken.TOKEN_PROGRAM_ID })
+        .remainingAccounts([
+          ...group(live, live.buyerAta),
+          ...group(wrongBuyerAta, wrongBuyerAta.sellerAta),
+          ...group(strayVault, strayVault.buyerAta, stray),
+          ...group(expired, expired.buyerAta),
+        ])
+        .rpc();
+      console.log("process_timeouts logs:", await getLogs(sig));
+    });
+
+    const e = await pg.program.account.escrow.fetch(expired.escrow);
+    assert.equal(e.state, 5 /* Refunded */);
+    assert.equal(await tokenBalance(expired.vaultAta), 0);
+    assert.equal(await tokenBalance(expired.buyerAta), buyerBefore + expired.amount);
+
+    assert.equal(await tokenBalance(stray), strayVault.amount);
+    for (const fx of [wrongBuyerAta, strayVault, live]) {
+      const skipped = await pg.program.account.escrow.fetch(fx.escrow);
+      assert.equal(skipped.state, 1 /* Open */);
+      assert.equal(await tokenBalance(fx.vaultAta), fx.amount);
+    }
+  });
+
+  it("payout accounts: foreign buyer, seller or treasury ATAs are refused", async () => {
+    const oracle = web3.Keypair.generate();
+    const fx = await setupEscrow({ oracles: [oracle] });
+    await pg.program.methods
+      .voteDelivery(Array(32).fill(0))
+      .accounts({ oracle: oracle.publicKey, escrow: fx.escrow })
+      .signers([oracle])
+      .rpc();
+
+    // Right mint, wrong owner
+    const strangerAta = await ensureAta(fx.mint, web3.Keypair.generate().publicKey);
+    const release = (override: Record<string, web3.PublicKey>) =>
+      pg.program.methods
+        .releasePayment()
+        .accounts({
+          escrow: fx.escrow,
+          config: fx.config,
+          vaultAuthority: fx.vaultAuth,
+          vaultAta: fx.vaultAta,
+          sellerAta: fx.sellerAta,
+          buyerAta: fx.buyerAta,
+          treasuryAta: fx.treasuryAta,
+          insuranceAta: fx.insuranceAta,
+          tokenProgram: splToken.TOKEN_PROGRAM_ID,
+          ...override,
+        })
+        .rpc();
+    for (const role of ["buyerAta", "sellerAta", "treasuryAta", "insuranceAta"]) {
+      await expectError(`release_payment with a foreign ${role}`, "BadTokenOwner", () => release({ [role]: strangerAta }));
+    }
+    assert.equal(await tokenBalance(fx.vaultAta), fx.amount);
+
+    await release({});
+    assert.ok((await tokenBalance(fx.sellerAta)) > 0);
+    assert.equal(await tokenBalance(strangerAta), 0);
+  });
+
+  it("proposals: only the counterparty accepts, rejection clears them, and they expire", async () => {
+    const fx = await setupEscrow();
+    const stranger = web3.Keypair.generate();
+    await airdrop(stranger.publicKey);
+    const now = Math.floor(Date.now() / 1000);
+    const deadlines = (by: web3.Keypair, call: any) =>
+      call.accounts({ actor: by.publicKey, escrow: fx.escrow }).signers([by]).rpc();
+
+    await deadlines(PAYER, pg.program.methods.proposeDeadlines(new BN(now + 86_400), new BN(now + 172_800)));
+    await expectError("proposer accepts its own deadlines", "Unauthorized", () =>
+      deadlines(PAYER, pg.program.methods.acceptDeadlines())
+    );
+    await expectError("stranger accepts deadlines", "Unauthorized", () =>
+      deadlines(stranger, pg.program.methods.acceptDeadlines())
+    );
+
+    await deadlines(fx.seller, pg.program.methods.rejectDeadlines());
+    let e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.deadlinesProposedBy.toBase58(), web3.PublicKey.default.toBase58());
+    assert.equal(e.pendingVerifyByTs.toNumber(), 0);
+    assert.equal(e.pendingDeliverByTs.toNumber(), 0);
+    assert.equal(e.deadlinesProposalExpiresTs.toNumber(), 0);
+    assert.equal(e.verifyByTs.toNumber(), 0, "rejected deadlines never applied");
+
+    await deadlines(
+      fx.seller,
+      pg.program.methods.proposeOracles([web3.Keypair.generate().publicKey, web3.Keypair.generate().publicKey], [1, 1], 2)
+    );
+    await deadlines(PAYER, pg.program.methods.rejectOracles());
+    e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.pendingOraclesLen, 0);
+    assert.equal(e.pendingQuorumWeight, 0);
+    assert.equal(e.oraclesProposedBy.toBase58(), web3.PublicKey.default.toBase58());
+    assert.equal(e.oraclesLen, 1, "current oracle set untouched");
+
+    // A 1-second TTL: the proposal is stale by the time the seller gets to it
+    await pg.program.methods
+      .setProposalTtl(new BN(1))
+      .accounts({ buyer: WALLET, seller: fx.seller.publicKey, escrow: fx.escrow })
+      .signers([PAYER, fx.seller])
+      .rpc();
+    await deadlines(PAYER, pg.program.methods.proposeDeadlines(new BN(now + 86_400), new BN(now + 172_800)));
+    await new Promise((r) => setTimeout(r, 3_000));
+    await expectError("accept after the TTL", "ProposalExpired", () => deadlines(fx.seller, pg.program.methods.acceptDeadlines()));
+  });
+
+  it("approve_cancel: only the other party can approve a cancel request", async () => {
+    const fx = await setupEscrow();
+    await pg.program.methods
+      .requestCancel()
+      .accounts({ actor: WALLET, escrow: fx.escrow })
+      .signers([PAYER])
+      .rpc();
+
+    const approve = (actor: web3.Keypair) =>
+      pg.program.methods
+        .approveCancel()
+        .accounts({
+          actor: actor.publicKey,
+          escrow: fx.escrow,
+          vaultAuthority: fx.vaultAuth,
+          vaultAta: fx.vaultAta,
+          buyerAta: fx.buyerAta,
+          tokenProgram: splToken.TOKEN_PROGRAM_ID,
+        })
+        .signers([actor])
+        .rpc();
+
+    const stranger = web3.Keypair.generate();
+    await airdrop(stranger.publicKey);
+    await expectError("approve_cancel by stranger", "Unauthorized", () => approve(stranger));
+    await expectError("approve_cancel by requester", "Unauthorized", () => approve(PAYER));
+
+    await logOnErr("approve_cancel", () => approve(fx.seller));
+    const e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.state, 5 /* Refunded */);
+    assert.equal(await tokenBalance(fx.vaultAta), 0);
+  });
+
+  it("change orders: an increase tops up the vault, a decrease refunds the buyer", async () => {
+    const fx = await setupEscrow({ amount: 100_000_000 });
+
+    const applyChangeOrder = async (newAmount: number, tag: number) => {
+      const docHash = Array(32).fill(tag);
+      await pg.program.methods
+        .submitChangeOrder(u64(newAmount), docHash)
+        .accounts({ actor: fx.seller.publicKey, escrow: fx.escrow })
+        .signers([fx.seller])
+        .rpc();
+      await logOnErr("approve_change_order", () =>
+        pg.program.methods
+          .approveChangeOrder(docHash)
+          .accounts({
+            buyer: WALLET,
+            seller: fx.seller.publicKey,
+            escrow: fx.escrow,
+            vaultAuthority: fx.vaultAuth,
+            vaultAta: fx.vaultAta,
+            buyerAta: fx.buyerAta,
+            tokenProgram: splToken.TOKEN_PROGRAM_ID,
+          })
+          .signers([PAYER, fx.seller])
+          .rpc()
+      );
+      return docHash;
+    };
+
+    // Increase: buyer → vault
+    const buyerStart = await tokenBalance(fx.buyerAta);
+    await applyChangeOrder(130_000_000, 1);
+    assert.equal(await tokenBalance(fx.vaultAta), 130_000_000);
+    assert.equal(await tokenBalance(fx.buyerAta), buyerStart - 30_000_000);
+
+    // Decrease: vault → buyer
+    const lastHash = await applyChangeOrder(80_000_000, 2);
+    assert.equal(await tokenBalance(fx.vaultAta), 80_000_000);
+    assert.equal(await tokenBalance(fx.buyerAta), buyerStart + 20_000_000);
+
+    const e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.amount.toNumber(), 80_000_000);
+    assert.equal(e.changeOrderCount, 2);
+    assert.deepEqual(Array.from(e.lastChangeOrderHash), lastHash);
+    assert.equal(e.changeOrderProposedBy.toBase58(), web3.PublicKey.default.toBase58());
+  });
+
+  it("change orders: released percentage milestones keep counting at what they paid", async () => {
+    const oracle = web3.Keypair.generate();
+    await airdrop(oracle.publicKey);
+    const fx = await setupEscrow({ amount: 100_000_000, oracles: [oracle] });
+    const evHash = Array(32).fill(0);
+
+    // Two 50% milestones; verify and release the first one at amount = 100
+    for (const id of [0, 1]) {
+      await pg.program.methods
+        .addMilestone(u64(0), 5_000, evHash, new BN(0), { none: {} }, 0, 0)
+        .accounts({
+          buyer: WALLET,
+          seller: fx.seller.publicKey,
+          escrow: fx.escrow,
+          milestone: pdaMilestone(fx.escrow, id)[0],
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .signers([PAYER, fx.seller])
+        .rpc();
+    }
+    const [m0] = pdaMilestone(fx.escrow, 0);
+    await pg.program.methods
+      .voteMilestone(0)
+      .accounts({ oracle: oracle.publicKey, escrow: fx.escrow, milestone: m0 })
+      .signers([oracle])
+      .rpc();
+    await logOnErr("release_for_milestone", () =>
+      pg.program.methods
+        .releaseForMilestone(0)
+        .accounts({
+          escrow: fx.escrow,
+          config: fx.config,
+          milestone: m0,
+          vaultAuthority: fx.vaultAuth,
+          vaultAta: fx.vaultAta,
+          sellerAta: fx.sellerAta,
+          buyerAta: fx.buyerAta,
+          treasuryAta: fx.treasuryAta,
+          insuranceAta: fx.insuranceAta,
+          tokenProgram: splToken.TOKEN_PROGRAM_ID,
+        })
+        .rpc()
+    );
+    const e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.milestonesReleasedShareBps, 5_000);
+    assert.equal(e.milestonesReleasedAmount.toNumber(), 50_000_000);
+
+    // 50 already paid + 50% of 60 > 60: the second milestone could no longer be funded
+    await expectError("submit_change_order below released milestones", "MilestoneOverTotal", () =>
+      pg.program.methods
+        .submitChangeOrder(u64(60_000_000), Array(32).fill(3))
+        .accounts({ actor: WALLET, escrow: fx.escrow })
+        .signers([PAYER])
+        .rpc()
+    );
+  });
+
+  // Verify the delivery with one direct oracle vote and release everything but retention
+  async function verifyAndRelease(fx: Fixture) {
+    const evHash = Array(32).fill(0);
+    await pg.program.methods
+      .voteDelivery(evHash)
+      .accounts({ oracle: fx.oracles[0].publicKey, escrow: fx.escrow })
+      .signers([fx.oracles[0]])
+      .rpc();
+    await pg.program.methods
+      .releasePayment()
+      .accounts({
+        escrow: fx.escrow,
+        config: fx.config,
+        vaultAuthority: fx.vaultAuth,
+        vaultAta: fx.vaultAta,
+        sellerAta: fx.sellerAta,
+        buyerAta: fx.buyerAta,
+        treasuryAta: fx.treasuryAta,
+        insuranceAta: fx.insuranceAta,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+  }
+
+  it("warranty claims: seller accepts one claim, arbiter splits a contested one", async () => {
+    const oracle = web3.Keypair.generate();
+    await airdrop(oracle.publicKey);
+    const fx = await setupEscrow({ oracles: [oracle], warrantyDays: 30 });
+    await logOnErr("verify + release", () => verifyAndRelease(fx));
+
+    const held = (await pg.program.account.escrow.fetch(fx.escrow)).retentionHeld.toNumber();
+    assert.ok(held > 0, "retention should be held after release_payment");
+    const claimAccounts = {
+      vaultAuthority: fx.vaultAuth,
+      vaultAta: fx.vaultAta,
+      buyerAta: fx.buyerAta,
+      tokenProgram: splToken.TOKEN_PROGRAM_ID,
+    };
+    const fileClaim = (amount: number) =>
+      pg.program.methods
+        .fileWarrantyClaim(u64(amount), Array(32).fill(7))
+        .accounts({ buyer: WALLET, escrow: fx.escrow })
+        .signers([PAYER])
+        .rpc();
+
+    // Claim 1: seller accepts, the claim is paid from retention
+    const claim1 = Math.floor(held / 4);
+    await fileClaim(claim1);
+    let e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.retentionFrozen.toNumber(), claim1);
+    const buyerBefore = await tokenBalance(fx.buyerAta);
+    await logOnErr("accept_warranty_claim", () =>
+      pg.program.methods
+        .acceptWarrantyClaim()
+        .accounts({ seller: fx.seller.publicKey, escrow: fx.escrow, ...claimAccounts })
+        .signers([fx.seller])
+        .rpc()
+    );
+    assert.equal(await tokenBalance(fx.buyerAta), buyerBefore + claim1);
+    e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.retentionHeld.toNumber(), held - claim1);
+    assert.equal(e.retentionClaimed.toNumber(), claim1);
+    assert.equal(e.retentionFrozen.toNumber(), 0);
+
+    // Claim 2: seller contests, Config.arbiter awards half of it
+    // Both init_config paths (happy path and ensureConfig) name this wallet as Config.arbiter
+    const cfg = await pg.program.account.config.fetch(fx.config);
+    assert.ok(cfg.arbiter.equals(WALLET), `Config.arbiter is ${cfg.arbiter.toBase58()}, not this wallet`);
+    const claim2 = Math.floor(held / 4);
+    await fileClaim(claim2);
+    await pg.program.methods
+      .contestWarrantyClaim()
+      .accounts({ seller: fx.seller.publicKey, escrow: fx.escrow })
+      .signers([fx.seller])
+      .rpc();
+    const award = Math.floor(claim2 / 2);
+    await logOnErr("resolve_warranty_claim", () =>
+      pg.program.methods
+        .resolveWarrantyClaim(u64(award))
+        .accounts({ config: fx.config, arbiter: WALLET, escrow: fx.escrow, ...claimAccounts })
+        .signers([PAYER])
+        .rpc()
+    );
+    e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.retentionHeld.toNumber(), held - claim1 - award);
+    assert.equal(e.retentionClaimed.toNumber(), claim1 + award);
+    assert.equal(e.retentionFrozen.toNumber(), 0);
+    assert.equal(e.warrantyClaimStatus, 0 /* None */);
+  });
+
+  it("dispute panel: a deadlocked 2-of-3 panel converges when an arbiter changes its vote", async () => {
+    await resetDisputeTerms();
+    const fx = await setupEscrow();
+    const panel = await newPanel(fx, 3, 2);
+    const d = await openDispute(fx, PAYER);
+    await submitEvidence(fx, d, fx.seller, 2); // respondent answers: voting opens
+
+    await castVote(fx, d, panel[0], { refund: {} });
+    await castVote(fx, d, panel[1], { release: {} });
+    await castVote(fx, d, panel[2], { split: {} }, 5_000);
+    let rec = await pg.program.account.disputeRecord.fetch(d.dispute);
+    assert.equal(rec.ruledTs.toNumber(), 0, "one vote per outcome: no ruling");
+
+    // panel[2] switches from Split to Refund: 2 of 3 agree
+    await castVote(fx, d, panel[2], { refund: {} });
+    const e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.disputeRefundVotes, 2);
+    assert.equal(e.disputeSplitVotes, 0);
+    rec = await pg.program.account.disputeRecord.fetch(d.dispute);
+    assert.ok(rec.ruledTs.toNumber() > 0);
+    assert.equal(rec.rulingOutcome, 0 /* Refund */);
+
+    const buyerBefore = await tokenBalance(fx.buyerAta);
+    await executeRuling(fx, d);
+    const after = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(after.state, 5 /* Refunded */);
+    assert.equal(after.disputeOpen, false);
+    assert.equal(await tokenBalance(fx.buyerAta), buyerBefore + fx.amount);
+  });
+
+  it("whole-escrow dispute: blocks milestone and full releases while open", async () => {
+    await resetDisputeTerms();
+    const oracle = web3.Keypair.generate();
+    await airdrop(oracle.publicKey);
+    const fx = await setupEscrow({ oracles: [oracle] });
+    const evHash = Array(32).fill(0);
+    const [m0] = pdaMilestone(fx.escrow, 0);
+    await pg.program.methods
+      .addMilestone(u64(fx.amount / 2), 0, evHash, new BN(0), { none: {} }, 0, 0)
+      .accounts({
+        buyer: WALLET,
+        seller: fx.seller.publicKey,
+        escrow: fx.escrow,
+        milestone: m0,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([PAYER, fx.seller])
+      .rpc();
+    await pg.program.methods
+      .voteMilestone(0)
+      .accounts({ oracle: oracle.publicKey, escrow: fx.escrow, milestone: m0 })
+      .signers([oracle])
+      .rpc();
+    assert.equal((await pg.program.account.escrow.fetch(fx.escrow)).state, 2 /* Verified */);
+
+    await openDispute(fx, PAYER); // no milestone: the whole escrow is disputed
+    const releaseAccounts = {
+      escrow: fx.escrow,
+      config: fx.config,
+      vaultAuthority: fx.vaultAuth,
+      vaultAta: fx.vaultAta,
+      sellerAta: fx.sellerAta,
+      buyerAta: fx.buyerAta,
+      treasuryAta: fx.treasuryAta,
+      insuranceAta: fx.insuranceAta,
+      tokenProgram: splToken.TOKEN_PROGRAM_ID,
+    };
+    await expectError("release_for_milestone during dispute", "BadState", () =>
+      pg.program.methods
+        .releaseForMilestone(0)
+        .accounts({ ...releaseAccounts, milestone: m0 })
+        .rpc()
+    );
+    await expectError("release_payment during dispute", "BadState", () =>
+      pg.program.methods.releasePayment().accounts(releaseAccounts).rpc()
+    );
+
+    assert.equal((await pg.program.account.milestone.fetch(m0)).released, false);
+    assert.equal(await tokenBalance(fx.vaultAta), fx.amount);
+  });
+
+  it("dispute bond: a winning claimant gets the bond back; no cancel or new dispute around it", async () => {
+    await resetDisputeTerms();
+    const bond = 1_000_000;
+    await pg.program.methods
+      .setDisputeBond(u64(bond), { counterparty: {} })
+      .accounts({ config: pdaConfig()[0], authority: WALLET })
+      .signers([PAYER])
+      .rpc();
+    const fx = await setupEscrow();
+    const [arbiter] = await newPanel(fx, 1, 1);
+    const buyerBefore = await tokenBalance(fx.buyerAta);
+
+    const d = await openDispute(fx, PAYER);
+    assert.equal(await tokenBalance(d.bondVault), bond);
+    assert.equal(await tokenBalance(fx.buyerAta), buyerBefore - bond);
+
+    // A cancel would refund the vault behind the arbiters' back
+    await pg.program.methods.requestCancel().accounts({ actor: fx.seller.publicKey, escrow: fx.escrow }).signers([fx.seller]).rpc();
+    await expectError("approve_cancel during dispute", "DisputeAlreadyOpen", () =>
+      pg.program.methods
+        .approveCancel()
+        .accounts({
+          actor: WALLET,
+          escrow: fx.escrow,
+          vaultAuthority: fx.vaultAuth,
+          vaultAta: fx.vaultAta,
+          buyerAta: fx.buyerAta,
+          tokenProgram: splToken.TOKEN_PROGRAM_ID,
+        })
+        .signers([PAYER])
+        .rpc()
+    );
+
+    await submitEvidence(fx, d, fx.seller, 2);
+    await castVote(fx, d, arbiter, { refund: {} });
+    await executeRuling(fx, d);
+    assert.equal(await tokenBalance(fx.buyerAta), buyerBefore + fx.amount, "refund plus the bond");
+    assert.equal(await pg.connection.getAccountInfo(d.bondVault), null, "bond vault closed");
+
+    await resetDisputeTerms();
+    await expectError("open_dispute on a refunded escrow", "BadState", () => openDispute(fx, PAYER));
+  });
+
+  it("arbiter fee: paid in full to the ruling arbiter even when the loser is awarded nothing", async () => {
+    await resetDisputeTerms();
+    await pg.program.methods
+      .setArbiterFee(500, { loser: {} })
+      .accounts({ config: pdaConfig()[0], authority: WALLET })
+      .signers([PAYER])
+      .rpc();
+    const fx = await setupEscrow();
+    const panel = await newPanel(fx, 2, 1);
+    const d = await openDispute(fx, PAYER);
+    await submitEvidence(fx, d, fx.seller, 2);
+    await castVote(fx, d, panel[1], { refund: {} });
+    const rec = await pg.program.account.disputeRecord.fetch(d.dispute);
+    assert.equal(rec.ruledBy.toBase58(), panel[1].publicKey.toBase58());
+
+    const buyerBefore = await tokenBalance(fx.buyerAta);
+    await executeRuling(fx, d);
+    const fee = fx.amount * 0.05;
+    assert.equal(await tokenBalance(await ensureAta(fx.mint, panel[1].publicKey)), fee);
+    assert.equal(await tokenBalance(await ensureAta(fx.mint, panel[0].publicKey)), 0);
+    assert.equal(await tokenBalance(fx.buyerAta), buyerBefore + fx.amount - fee);
+    assert.equal(await tokenBalance(fx.sellerAta), 0);
+    await resetDisputeTerms();
+  });
+
+  it("appeal: a refund ruling is appealed, overturned before the decision deadline, and executed", async () => {
+    await resetDisputeTerms();
+    const [config] = pdaConfig();
+    const appealArbiter = web3.Keypair.generate();
+    await airdrop(appealArbiter.publicKey);
+    await pg.program.methods
+      .setAppealTerms(new BN(3600), appealArbiter.publicKey, u64(0))
+      .accounts({ config, authority: WALLET })
+      .signers([PAYER])
+      .rpc();
+    await pg.program.methods.setArbiterFee(500, { loser: {} }).accounts({ config, authority: WALLET }).signers([PAYER]).rpc();
+    const fx = await setupEscrow();
+    const [arbiter] = await newPanel(fx, 1, 1);
+    const d = await openDispute(fx, PAYER);
+    await submitEvidence(fx, d, fx.seller, 2);
+    await castVote(fx, d, arbiter, { refund: {} });
+    await expectError("execute_ruling inside the appeal window", "AppealWindowOpen", () => executeRuling(fx, d));
+
+    const [appealBondVault] = pdaAppealBondVault(fx.escrow, d.no);
+    await pg.program.methods
+      .appealRuling(Array(32).fill(4))
+      .accounts({
+        actor: fx.seller.publicKey,
+        config,
+        escrow: fx.escrow,
+        mint: fx.mint,
+        dispute: d.dispute,
+        vaultAuthority: fx.vaultAuth,
+        appealBondVault,
+        actorAta: fx.sellerAta,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([fx.seller])
+      .rpc();
+    let rec = await pg.program.account.disputeRecord.fetch(d.dispute);
+    assert.equal(rec.appealDecideByTs.toNumber(), rec.appealedTs.toNumber() + 14 * 24 * 60 * 60);
+
+    // Undecided and before the decision deadline: the ruling cannot execute yet
+    const appealAccounts = { appealBondVault, appellant: fx.seller.publicKey };
+    await expectError("execute_ruling with a pending appeal", "AppealPending", () => executeRuling(fx, d, appealAccounts));
+
+    await pg.program.methods
+      .decideAppeal({ release: {} }, 0)
+      .accounts({ config, appealArbiter: appealArbiter.publicKey, escrow: fx.escrow, dispute: d.dispute })
+      .signers([appealArbiter])
+      .rpc();
+    rec = await pg.program.account.disputeRecord.fetch(d.dispute);
+    assert.equal(rec.appealDecided, true);
+    assert.equal(rec.rulingOutcome, 1 /* Release */);
+    assert.equal(rec.ruledBy.toBase58(), appealArbiter.publicKey.toBase58(), "the overturning arbiter ruled");
+
+    const buyerBefore = await tokenBalance(fx.buyerAta);
+    await executeRuling(fx, d, appealAccounts);
+    const e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.state, 4 /* Released */);
+    assert.equal(await tokenBalance(fx.buyerAta), buyerBefore);
+    assert.ok((await tokenBalance(fx.sellerAta)) > 0);
+    // The 5% fee goes to the appeal arbiter, not the overturned panel arbiter
+    const appealArbiterAta = await splToken.getAssociatedTokenAddress(fx.mint, appealArbiter.publicKey, true);
+    assert.equal(await tokenBalance(appealArbiterAta), fx.amount * 0.05);
+    const panelAta = await splToken.getAssociatedTokenAddress(fx.mint, arbiter.publicKey, true);
+    assert.equal(await pg.connection.getAccountInfo(panelAta), null, "panel arbiter paid nothing");
+    assert.equal(await pg.connection.getAccountInfo(appealBondVault), null, "appeal bond vault closed");
+    await resetDisputeTerms();
+  });
+
+  // Relay delivery attestations (Ed25519 pre-instructions) to verify_delivery
+  async function relayDeliveryAttestations(fx: Fixture, ixs: web3.TransactionInstruction[]) {
+    await pg.program.methods
+      .verifyDelivery(fx.projectId, Array(32).fill(0))
+      .accounts({ escrow: fx.escrow, instructions: web3.SYSVAR_INSTRUCTIONS_PUBKEY })
+      .preInstructions(ixs)
+      .rpc();
+  }
+
+  it("attestations: an oracle's repeated attestation counts once toward quorum", async () => {
+    const oracles = [web3.Keypair.generate(), web3.Keypair.generate()];
+    const fx = await setupEscrow({ oracles, quorumWeight: 2 });
+    const evHash = Array(32).fill(0);
+    const expiry = Math.floor(Date.now() / 1000) + 3600;
+    const att = (o: web3.Keypair, exp: number) =>
+      attestationIx(o, fx.escrow, fx.projectId, DELIVERY_ATTESTATION_ID, evHash, exp);
+
+    // Same oracle twice (even with different expiries) is still one vote of weight 1
+    await relayDeliveryAttestations(fx, [att(oracles[0], expiry), att(oracles[0], expiry + 60)]);
+    let e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.verifiedTs.toNumber(), 0);
+    assert.equal(e.deliveryVotes, 0b01);
+
+    await relayDeliveryAttestations(fx, [att(oracles[1], expiry)]);
+    e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.ok(e.verifiedTs.toNumber() > 0);
+    assert.equal(e.state, 2 /* Verified */);
+  });
+
+  it("vote withdrawal: a withdrawn oracle's earlier attestation cannot restore its vote", async () => {
+    const oracles = [web3.Keypair.generate(), web3.Keypair.generate()];
+    await airdrop(oracles[0].publicKey);
+    const fx = await setupEscrow({ oracles, quorumWeight: 2 });
+    const evHash = Array(32).fill(0);
+    const expiry = Math.floor(Date.now() / 1000) + 3600;
+    const signed = attestationIx(oracles[0], fx.escrow, fx.projectId, DELIVERY_ATTESTATION_ID, evHash, expiry);
+
+    await relayDeliveryAttestations(fx, [signed]);
+    assert.equal((await pg.program.account.escrow.fetch(fx.escrow)).deliveryVotes, 0b01);
+
+    await pg.program.methods
+      .withdrawDeliveryVote()
+      .accounts({ oracle: oracles[0].publicKey, escrow: fx.escrow })
+      .signers([oracles[0]])
+      .rpc();
+    let e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.deliveryVotes, 0);
+    assert.equal(e.oracleNonces[0], 1);
+
+    // The still-unexpired attestation was signed under nonce 0: no vote is recorded
+    await expectError("replayed attestation", "NoOracleVote", () => relayDeliveryAttestations(fx, [signed]));
+
+    // The oracle changes its mind again and signs under the new nonce
+    await relayDeliveryAttestations(fx, [
+      attestationIx(oracles[0], fx.escrow, fx.projectId, DELIVERY_ATTESTATION_ID, evHash, expiry, 1),
+    ]);
+    e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.deliveryVotes, 0b01);
+    assert.equal(e.verifiedTs.toNumber(), 0, "one of two votes: not verified yet");
+  });
+
+  async function oracleDo(fx: Fixture, oracle: web3.Keypair, call: any) {
+    await call.accounts({ oracle: oracle.publicKey, escrow: fx.escrow }).signers([oracle]).rpc();
+  }
+
+  it("oracle rejections: a veto blocks a quorum of approvals until it is cleared", async () => {
+    const oracles = [web3.Keypair.generate(), web3.Keypair.generate(), web3.Keypair.generate()];
+    const fx = await setupEscrow({ oracles, quorumWeight: 2 });
+    const evHash = Array(32).fill(0);
+    await pg.program.methods
+      .setVetoWeight(1)
+      .accounts({ buyer: WALLET, seller: fx.seller.publicKey, escrow: fx.escrow })
+      .signers([PAYER, fx.seller])
+      .rpc();
+
+    await oracleDo(fx, oracles[2], pg.program.methods.rejectDelivery(7, Array(32).fill(5)));
+    await oracleDo(fx, oracles[0], pg.program.methods.voteDelivery(evHash));
+    await oracleDo(fx, oracles[1], pg.program.methods.voteDelivery(evHash));
+    let e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.verifiedTs.toNumber(), 0, "quorum met but vetoed");
+    assert.equal(e.state, 1 /* Open */);
+
+    // Defects fixed: lifting the veto verifies on the votes already recorded
+    await oracleDo(fx, oracles[2], pg.program.methods.clearDeliveryRejection());
+    e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.ok(e.verifiedTs.toNumber() > 0);
+    assert.equal(e.state, 2 /* Verified */);
+  });
+
+  it("oracle rejections: delivery escalation is undone on clearing; milestone escalation stays on the milestone", async () => {
+    const oracles = Array.from({ length: 4 }, () => web3.Keypair.generate());
+    const fx = await setupEscrow({ oracles, quorumWeight: 2 });
+    const evHash = Array(32).fill(0);
+
+    // Two rejections reach the quorum weight: Open → Dispute
+    await oracleDo(fx, oracles[2], pg.program.methods.rejectDelivery(7, evHash));
+    await oracleDo(fx, oracles[3], pg.program.methods.rejectDelivery(7, evHash));
+    let e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.state, 6 /* Dispute */);
+    assert.equal(e.escalatedFromState, 1 /* Open */);
+    assert.equal(e.disputeOpen, false);
+
+    // No mutual cancel around the escalation: the funds are headed for a ruling
+    await pg.program.methods.requestCancel().accounts({ actor: WALLET, escrow: fx.escrow }).signers([PAYER]).rpc();
+    await expectError("approve_cancel while escalated", "BadState", () =>
+      pg.program.methods
+        .approveCancel()
+        .accounts({
+          actor: fx.seller.publicKey,
+          escrow: fx.escrow,
+          vaultAuthority: fx.vaultAuth,
+          vaultAta: fx.vaultAta,
+          buyerAta: fx.buyerAta,
+          tokenProgram: splToken.TOKEN_PROGRAM_ID,
+        })
+        .signers([fx.seller])
+        .rpc()
+    );
+    assert.equal(await tokenBalance(fx.vaultAta), fx.amount);
+
+    // One rejection is lifted before anyone opened a dispute: back to Open
+    await oracleDo(fx, oracles[3], pg.program.methods.clearDeliveryRejection());
+    e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.state, 1 /* Open */);
+    assert.equal(e.escalatedFromState, 0);
+
+    const [m0] = pdaMilestone(fx.escrow, 0);
+    await pg.program.methods
+      .addMilestone(u64(fx.amount / 2), 0, evHash, new BN(0), { none: {} }, 0, 0)
+      .accounts({
+        buyer: WALLET,
+        seller: fx.seller.publicKey,
+        escrow: fx.escrow,
+        milestone: m0,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([PAYER, fx.seller])
+      .rpc();
+    const onMilestone = (o: web3.Keypair, call: any) =>
+      call.accounts({ oracle: o.publicKey, escrow: fx.escrow, milestone: m0 }).signers([o]).rpc();
+
+    // Milestone rejections at quorum leave the escrow alone but block the milestone
+    await onMilestone(oracles[2], pg.program.methods.rejectMilestone(0, 7, evHash));
+    await onMilestone(oracles[3], pg.program.methods.rejectMilestone(0, 7, evHash));
+    assert.equal((await pg.program.account.escrow.fetch(fx.escrow)).state, 1 /* Open */);
+    assert.equal((await pg.program.account.milestone.fetch(m0)).escalated, true, "escalation visible on the milestone");
+    await onMilestone(oracles[0], pg.program.methods.voteMilestone(0));
+    await onMilestone(oracles[1], pg.program.methods.voteMilestone(0));
+    assert.equal((await pg.program.account.milestone.fetch(m0)).verified, false);
+
+    await onMilestone(oracles[3], pg.program.methods.clearMilestoneRejection(0));
+    const cleared = await pg.program.account.milestone.fetch(m0);
+    assert.equal(cleared.escalated, false);
+    assert.equal(cleared.verified, true);
+    assert.equal((await pg.program.account.escrow.fetch(fx.escrow)).state, 2 /* Verified */);
+  });
+
+  // Add milestone `id` (both parties sign) with its own deadline and penalty schedule
+  async function addMilestone(
+    fx: Fixture,
+    id: number,
+    amount: number,
+    dueTs = 0,
+    kind: any = { none: {} },
+    bps = 0,
+    capBps = 0
+  ) {
+    const [milestone] = pdaMilestone(fx.escrow, id);
+    await pg.program.methods
+      .addMilestone(u64(amount), 0, Array(32).fill(0), new BN(dueTs), kind, bps, capBps)
+      .accounts({
+        buyer: WALLET,
+        seller: fx.seller.publicKey,
+        escrow: fx.escrow,
+        milestone,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([PAYER, fx.seller])
+      .rpc();
+    return milestone;
+  }
+
+  function releaseAccountsOf(fx: Fixture) {
+    return {
+      escrow: fx.escrow,
+      config: fx.config,
+      vaultAuthority: fx.vaultAuth,
+      vaultAta: fx.vaultAta,
+      sellerAta: fx.sellerAta,
+      buyerAta: fx.buyerAta,
+      treasuryAta: fx.treasuryAta,
+      insuranceAta: fx.insuranceAta,
+      tokenProgram: splToken.TOKEN_PROGRAM_ID,
+    };
+  }
+
+  // Penalty withheld from a `gross` payout: retention, then fees, then `penaltyBps` of the rest
+  async function expectedPenalty(fx: Fixture, gross: number, penaltyBps: number) {
+    const e = await pg.program.account.escrow.fetch(fx.escrow);
+    const paidOut = gross - Math.floor((gross * e.retentionBps) / 10_000);
+    const fees = Math.floor((paidOut * e.feeBps) / 10_000) + Math.floor((paidOut * e.insuranceBps) / 10_000);
+    return Math.floor(((paidOut - fees) * penaltyBps) / 10_000);
+  }
+
+  it("late penalties: a milestone's own due_ts and schedule win over the escrow-wide one", async () => {
+    const oracle = web3.Keypair.generate();
+    await airdrop(oracle.publicKey);
+    const fx = await setupEscrow({ oracles: [oracle] });
+    const DAY = 86_400;
+    const now = Math.floor(Date.now() / 1000);
+
+    // Escrow-wide: delivery 1.5 days late, 300 bps per started day → 600 bps
+    await setDeadlines(fx, now + 30 * DAY, now - 1.5 * DAY);
+    await pg.program.methods
+      .setLatePenalty(new BN(0), 300, 1_000)
+      .accounts({ buyer: WALLET, seller: fx.seller.publicKey, escrow: fx.escrow })
+      .signers([PAYER, fx.seller])
+      .rpc();
+
+    const gross = 20_000_000;
+    const cases = [
+      // PerDay 100 bps, 2.5 days late → 300, capped at 250
+      { dueTs: now - 2.5 * DAY, kind: { perDay: {} }, bps: 100, cap: 250, expectBps: 250 },
+      // Flat 400 bps, half a day late
+      { dueTs: now - 0.5 * DAY, kind: { flat: {} }, bps: 400, cap: 0, expectBps: 400 },
+      // Due long ago but no penalty agreed: the milestone schedule still applies, not the escrow's
+      { dueTs: now - 10 * DAY, kind: { none: {} }, bps: 0, cap: 0, expectBps: 0 },
+      // No due_ts: falls back to the escrow-wide schedule
+      { dueTs: 0, kind: { none: {} }, bps: 0, cap: 0, expectBps: 600 },
+    ];
+    for (const [id, c] of cases.entries()) {
+      await addMilestone(fx, id, gross, c.dueTs, c.kind, c.bps, c.cap);
+    }
+
+    for (const [id, c] of cases.entries()) {
+      const milestone = pdaMilestone(fx.escrow, id)[0];
+      await pg.program.methods
+        .voteMilestone(id)
+        .accounts({ oracle: oracle.publicKey, escrow: fx.escrow, milestone })
+        .signers([oracle])
+        .rpc();
+      const expected = await expectedPenalty(fx, gross, c.expectBps);
+      const buyerBefore = await tokenBalance(fx.buyerAta);
+      await logOnErr(`release_for_milestone ${id}`, () =>
+        pg.program.methods
+          .releaseForMilestone(id)
+          .accounts({ ...releaseAccountsOf(fx), milestone })
+          .rpc()
+      );
+      console.log(`milestone ${id}: penalty ${expected} (${c.expectBps} bps)`);
+      assert.equal(await tokenBalance(fx.buyerAta), buyerBefore + expected);
+    }
+  });
+
+  it("late penalties: grace period, per-day accrual and cap on the escrow-wide schedule", async () => {
+    const DAY = 86_400;
+    const now = Math.floor(Date.now() / 1000);
+    const cases = [
+      // 2.5 days late inside a 3-day grace period: nothing
+      { grace: 3 * DAY, perDay: 100, cap: 500, expectBps: 0 },
+      // 1.5 days past a 1-day grace: 2 started days × 100
+      { grace: DAY, perDay: 100, cap: 500, expectBps: 200 },
+      // 3 started days × 100, capped at 250
+      { grace: 0, perDay: 100, cap: 250, expectBps: 250 },
+    ];
+    for (const c of cases) {
+      const fx = await setupEscrow();
+      await setDeadlines(fx, now + 30 * DAY, now - 2.5 * DAY);
+      const setPenalty = (grace: number, perDay: number, cap: number) =>
+        pg.program.methods
+          .setLatePenalty(new BN(grace), perDay, cap)
+          .accounts({ buyer: WALLET, seller: fx.seller.publicKey, escrow: fx.escrow })
+          .signers([PAYER, fx.seller])
+          .rpc();
+      if (c === cases[0]) {
+        // A cap below one day's accrual would quietly disable the penalty
+        await expectError("set_late_penalty with zero cap", "BadBps", () => setPenalty(0, 100, 0));
+        await expectError("set_late_penalty with cap under one day", "BadBps", () => setPenalty(0, 100, 50));
+      }
+      await setPenalty(c.grace, c.perDay, c.cap);
+
+      await pg.program.methods
+        .voteDelivery(Array(32).fill(0))
+        .accounts({ oracle: fx.oracles[0].publicKey, escrow: fx.escrow })
+        .signers([fx.oracles[0]])
+        .rpc();
+      const expected = await expectedPenalty(fx, fx.amount, c.expectBps);
+      const buyerBefore = await tokenBalance(fx.buyerAta);
+      const sig = await logOnErr("release_payment", () =>
+        pg.program.methods.releasePayment().accounts(releaseAccountsOf(fx)).rpc()
+      );
+      const [released] = await eventsOf(sig, "PaymentReleased");
+      console.log(`grace ${c.grace}s, ${c.perDay}/day cap ${c.cap}: penalty ${released.penalty}`);
+      assert.equal(released.penalty.toNumber(), expected);
+      assert.equal(await tokenBalance(fx.buyerAta), buyerBefore + expected);
+    }
+  });
+
+  it("milestone edits: both parties, within the total, and only while unverified and undisputed", async () => {
+    await resetDisputeTerms();
+    const oracle = web3.Keypair.generate();
+    await airdrop(oracle.publicKey);
+    const fx = await setupEscrow({ amount: 100_000_000, oracles: [oracle] });
+    const [m0, m1, m2] = [
+      await addMilestone(fx, 0, 40_000_000),
+      await addMilestone(fx, 1, 30_000_000),
+      await addMilestone(fx, 2, 20_000_000),
+    ];
+    const update = (id: number, milestone: web3.PublicKey, amount: number, seller = fx.seller) =>
+      pg.program.methods
+        .updateMilestone(id, u64(amount), 0, Array(32).fill(id))
+        .accounts({ buyer: WALLET, seller: seller.publicKey, escrow: fx.escrow, milestone })
+        .signers(seller === PAYER ? [PAYER] : [PAYER, seller])
+        .rpc();
+    const remove = (id: number, milestone: web3.PublicKey) =>
+      pg.program.methods
+        .removeMilestone(id)
+        .accounts({ buyer: WALLET, seller: fx.seller.publicKey, escrow: fx.escrow, milestone })
+        .signers([PAYER, fx.seller])
+        .rpc();
+
+    // 40 + 70 + 20 > 100
+    await expectError("update_milestone over total", "MilestoneOverTotal", () => update(1, m1, 70_000_000));
+    // The buyer alone cannot pose as the seller
+    await expectError("update_milestone by one party", "ConstraintHasOne", () => update(1, m1, 35_000_000, PAYER));
+    await update(1, m1, 35_000_000);
+    assert.equal((await pg.program.account.escrow.fetch(fx.escrow)).milestonesTotal.toNumber(), 95_000_000);
+
+    // Verified milestones are locked
+    await pg.program.methods
+      .voteMilestone(0)
+      .accounts({ oracle: oracle.publicKey, escrow: fx.escrow, milestone: m0 })
+      .signers([oracle])
+      .rpc();
+    await expectError("update_milestone after verification", "AlreadyVerified", () => update(0, m0, 10_000_000));
+    await expectError("remove_milestone after verification", "AlreadyVerified", () => remove(0, m0));
+
+    // So is one under a scoped dispute
+    await openDispute(fx, PAYER, m2);
+    await expectError("update_milestone while disputed", "MilestoneDisputed", () => update(2, m2, 10_000_000));
+    await expectError("remove_milestone while disputed", "MilestoneDisputed", () => remove(2, m2));
+
+    // Removing the open one closes its PDA and drops it from the schedule
+    await remove(1, m1);
+    assert.equal(await pg.connection.getAccountInfo(m1), null);
+    assert.equal((await pg.program.account.escrow.fetch(fx.escrow)).milestonesTotal.toNumber(), 60_000_000);
+  });
+
+  it("retention schedule: half at completion, the rest when the second tranche matures", async () => {
+    const fx = await setupEscrow();
+    const chainNow = async () => (await pg.connection.getBlockTime(await pg.connection.getSlot())) ?? 0;
+    const secondTs = (await chainNow()) + 10;
+    await pg.program.methods
+      .setRetentionSchedule([new BN(secondTs - 86_400), new BN(secondTs)], [5_000, 5_000])
+      .accounts({ buyer: WALLET, seller: fx.seller.publicKey, escrow: fx.escrow })
+      .signers([PAYER, fx.seller])
+      .rpc();
+    await verifyAndRelease(fx);
+    const held = (await pg.program.account.escrow.fetch(fx.escrow)).retentionHeld.toNumber();
+    assert.ok(held > 0, "retention withheld at release");
+
+    const releaseRetention = () => pg.program.methods.releaseRetention().accounts(releaseAccountsOf(fx)).rpc();
+    const vaultBefore = await tokenBalance(fx.vaultAta);
+    await logOnErr("release_retention (first tranche)", releaseRetention);
+    let e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.retentionPaid.toNumber(), Math.floor(held / 2));
+    assert.equal(e.retentionTranchesReleased, 0b01);
+    assert.equal(await tokenBalance(fx.vaultAta), vaultBefore - Math.floor(held / 2));
+
+    // The first tranche is spent; the second has not matured yet
+    await expectError("release_retention twice", "RetentionAlreadyReleased", releaseRetention);
+
+    while ((await chainNow()) <= secondTs) await new Promise((r) => setTimeout(r, 1_000));
+    await logOnErr("release_retention (second tranche)", releaseRetention);
+    e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.retentionPaid.toNumber(), held);
+    assert.equal(e.retentionHeld.toNumber(), 0);
+    assert.equal(e.retentionTranchesReleased, 0b11);
+    assert.equal(e.retentionReleased, true);
+    assert.equal(await tokenBalance(fx.vaultAta), vaultBefore - held);
+  });
+
+  it("warranty: bounded by Config, starts at verified delivery, or at a release with nothing left", async () => {
+    const cfg = await pg.program.account.config.fetch(await ensureConfig());
+    const maxDays = cfg.maxWarrantyDays.toNumber();
+    const minDays = cfg.minWarrantyDays.toNumber();
+    await expectError("create_escrow above max warranty", "BadWarrantyPeriod", () =>
+      setupEscrow({ warrantyDays: maxDays + 1 })
+    );
+    await expectError("create_escrow below min warranty", "BadWarrantyPeriod", () =>
+      setupEscrow({ warrantyDays: minDays - 1 })
+    );
+
+    // Overall verification starts the clock
+    const days = Math.max(minDays, Math.min(30, maxDays));
+    const fx = await setupEscrow({ warrantyDays: days });
+    await pg.program.methods
+      .voteDelivery(Array(32).fill(0))
+      .accounts({ oracle: fx.oracles[0].publicKey, escrow: fx.escrow })
+      .signers([fx.oracles[0]])
+      .rpc();
+    let e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.ok(e.verifiedTs.toNumber() > 0);
+    assert.equal(e.warrantyEndTs.toNumber(), e.verifiedTs.toNumber() + days * 86_400);
+
+    // Milestones pay the whole amount without overall verification: release_payment has
+    // nothing left to pay but still closes the escrow and starts the clock
+    const oracle = web3.Keypair.generate();
+    await airdrop(oracle.publicKey);
+    const fx2 = await setupEscrow({ oracles: [oracle], warrantyDays: days });
+    for (const id of [0, 1]) {
+      const milestone = await addMilestone(fx2, id, fx2.amount / 2);
+      await pg.program.methods
+        .voteMilestone(id)
+        .accounts({ oracle: oracle.publicKey, escrow: fx2.escrow, milestone })
+        .signers([oracle])
+        .rpc();
+      await pg.program.methods
+        .releaseForMilestone(id)
+        .accounts({ ...releaseAccountsOf(fx2), milestone })
+        .rpc();
+    }
+    e = await pg.program.account.escrow.fetch(fx2.escrow);
+    assert.equal(e.verifiedTs.toNumber(), 0);
+    assert.equal(e.warrantyEndTs.toNumber(), 0);
+    const vaultBefore = await tokenBalance(fx2.vaultAta);
+    await logOnErr("release_payment with nothing left", () =>
+      pg.program.methods.releasePayment().accounts(releaseAccountsOf(fx2)).rpc()
+    );
+    e = await pg.program.account.escrow.fetch(fx2.escrow);
+    assert.equal(e.state, 4 /* Released */);
+    assert.equal(e.warrantyEndTs.toNumber(), e.releasedTs.toNumber() + days * 86_400);
+    assert.equal(await tokenBalance(fx2.vaultAta), vaultBefore, "retention stays in the vault");
+  });
+
+  it("dispute panel: a Config panel change mid-dispute does not change who votes on it", async () => {
+    await resetDisputeTerms();
+    const [config] = pdaConfig();
+    const setConfigPanel = (arbiters: web3.PublicKey[], threshold: number) =>
+      pg.program.methods.setArbiterPanel(arbiters, threshold).accounts({ config, authority: WALLET }).signers([PAYER]).rpc();
+    const panel = [web3.Keypair.generate(), web3.Keypair.generate()];
+    for (const a of panel) await airdrop(a.publicKey);
+
+    const fx = await setupEscrow(); // no escrow panel: disputes use Config's
+    await setConfigPanel(panel.map((a) => a.publicKey), 2);
+    const d = await openDispute(fx, PAYER);
+    // Back to the default 1-of-1 panel while the dispute is open
+    await setConfigPanel([WALLET], 1);
+
+    const rec = await pg.program.account.disputeRecord.fetch(d.dispute);
+    assert.equal(rec.arbitersLen, 2);
+    assert.equal(rec.arbiterThreshold, 2);
+
+    await submitEvidence(fx, d, fx.seller, 2);
+    await expectError("vote from the new Config panel", "NotArbiter", () => castVote(fx, d, PAYER, { release: {} }));
+    await castVote(fx, d, panel[0], { refund: {} });
+    assert.equal((await pg.program.account.disputeRecord.fetch(d.dispute)).ruledTs.toNumber(), 0, "1 of 2 votes");
+    await castVote(fx, d, panel[1], { refund: {} });
+    const ruled = await pg.program.account.disputeRecord.fetch(d.dispute);
+    assert.ok(ruled.ruledTs.toNumber() > 0);
+    assert.equal(ruled.ruledBy.toBase58(), panel[1].publicKey.toBase58());
+  });
+
+  it("dispute response: no votes before the respondent answers; a default ruling after the window", async () => {
+    await resetDisputeTerms();
+    const [config] = pdaConfig();
+
+    // Default 7-day window, no response yet
+    const fx = await setupEscrow();
+    const [arbiter] = await newPanel(fx, 1, 1);
+    const d = await openDispute(fx, PAYER);
+    await expectError("vote before the response", "AwaitingResponse", () => castVote(fx, d, arbiter, { refund: {} }));
+
+    // A 2-second window: the seller never answers and the panel rules by default
+    await pg.program.methods.setDisputeResponseWindow(new BN(2)).accounts({ config, authority: WALLET }).signers([PAYER]).rpc();
+    const fx2 = await setupEscrow();
+    const [arbiter2] = await newPanel(fx2, 1, 1);
+    const d2 = await openDispute(fx2, PAYER);
+    await new Promise((r) => setTimeout(r, 4_000));
+    await castVote(fx2, d2, arbiter2, { refund: {} });
+    const rec = await pg.program.account.disputeRecord.fetch(d2.dispute);
+    assert.ok(rec.ruledTs.toNumber() > rec.responseDeadlineTs.toNumber());
+    assert.equal(rec.byDefault, true);
+    assert.equal(rec.respondedTs.toNumber(), 0);
+
+    // The record the panel ruled on is final
+    await expectError("evidence after the ruling", "RulingAlreadyPosted", () => submitEvidence(fx2, d2, fx2.seller, 5));
+    await resetDisputeTerms();
+  });
+
+  it("milestone dispute: freezes one milestone, the rest releases, the ruling splits only it", async () => {
+    await resetDisputeTerms();
+    const oracle = web3.Keypair.generate();
+    await airdrop(oracle.publicKey);
+    const fx = await setupEscrow({ amount: 100_000_000, oracles: [oracle] });
+    const [arbiter] = await newPanel(fx, 1, 1);
+    const m0 = await addMilestone(fx, 0, 40_000_000);
+    const m1 = await addMilestone(fx, 1, 40_000_000);
+
+    const d = await openDispute(fx, PAYER, m1);
+    let e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.disputeFrozen.toNumber(), 40_000_000);
+    assert.equal(e.state, 1 /* Open: only the milestone is disputed */);
+    assert.equal((await pg.program.account.milestone.fetch(m1)).disputed, true);
+    assert.equal((await pg.program.account.milestone.fetch(m0)).disputed, false);
+
+    // The other milestone verifies and releases as usual
+    await pg.program.methods
+      .voteMilestone(0)
+      .accounts({ oracle: oracle.publicKey, escrow: fx.escrow, milestone: m0 })
+      .signers([oracle])
+      .rpc();
+    const sellerBefore = await tokenBalance(fx.sellerAta);
+    await logOnErr("release_for_milestone beside a scoped dispute", () =>
+      pg.program.methods.releaseForMilestone(0).accounts({ ...releaseAccountsOf(fx), milestone: m0 }).rpc()
+    );
+    assert.ok((await tokenBalance(fx.sellerAta)) > sellerBefore);
+    assert.equal((await pg.program.account.escrow.fetch(fx.escrow)).disputeFrozen.toNumber(), 40_000_000);
+
+    // 40% to the seller, 60% back to the buyer, out of the frozen 40M only
+    await submitEvidence(fx, d, fx.seller, 2);
+    await castVote(fx, d, arbiter, { split: {} }, 4_000);
+    const vaultBefore = await tokenBalance(fx.vaultAta);
+    const buyerBefore = await tokenBalance(fx.buyerAta);
+    const sellerMid = await tokenBalance(fx.sellerAta);
+    await executeRuling(fx, d);
+
+    e = await pg.program.account.escrow.fetch(fx.escrow);
+    const sellerGross = 16_000_000;
+    const retention = Math.floor((sellerGross * e.retentionBps) / 10_000);
+    const paidOut = sellerGross - retention;
+    const sellerNet =
+      paidOut - Math.floor((paidOut * e.feeBps) / 10_000) - Math.floor((paidOut * e.insuranceBps) / 10_000);
+    assert.equal(await tokenBalance(fx.buyerAta), buyerBefore + 24_000_000);
+    assert.equal(await tokenBalance(fx.sellerAta), sellerMid + sellerNet);
+    assert.equal(await tokenBalance(fx.vaultAta), vaultBefore - 40_000_000 + retention);
+    assert.equal(e.disputeFrozen.toNumber(), 0);
+    assert.equal(e.disputeOpen, false);
+    assert.notEqual(e.state, 6 /* Dispute */);
+    const settled = await pg.program.account.milestone.fetch(m1);
+    assert.equal(settled.released, true);
+    assert.equal(settled.disputed, false);
+  });
+
+  it("oracle sets: duplicates, the default key and unreachable quorums are refused", async () => {
+    const [a, b] = [web3.Keypair.generate(), web3.Keypair.generate()];
+    // setupEscrow only reads `publicKey`, so the default key can stand in for an oracle
+    const defaultKey = { publicKey: web3.PublicKey.default } as web3.Keypair;
+    const bad = [
+      { label: "duplicate oracle", oracles: [a, a], weights: [1, 1], quorum: 1, code: "BadOracle" },
+      { label: "default oracle key", oracles: [a, defaultKey], weights: [1, 1], quorum: 1, code: "BadOracle" },
+      { label: "quorum above oracle count", oracles: [a, b], weights: [1, 1], quorum: 3, code: "BadQuorum" },
+      { label: "quorum above total weight", oracles: [a, b], weights: [2, 3], quorum: 6, code: "BadQuorum" },
+    ];
+
+    for (const c of bad) {
+      await expectError(`create_escrow: ${c.label}`, c.code, () =>
+        setupEscrow({ oracles: c.oracles, weights: c.weights, quorumWeight: c.quorum })
+      );
+    }
+
+    const fx = await setupEscrow();
+    for (const c of bad) {
+      await expectError(`propose_oracles: ${c.label}`, c.code, () =>
+        pg.program.methods
+          .proposeOracles(c.oracles.map((o) => o.publicKey), c.weights, c.quorum)
+          .accounts({ actor: WALLET, escrow: fx.escrow })
+          .signers([PAYER])
+          .rpc()
+      );
+    }
+    // Nothing was left pending by the refused proposals
+    assert.equal((await pg.program.account.escrow.fetch(fx.escrow)).pendingOraclesLen, 0);
+  });
+
+  it("weighted quorum: one heavy oracle verifies alone, two light ones fall short", async () => {
+    const [heavy, light1, light2] = [web3.Keypair.generate(), web3.Keypair.generate(), web3.Keypair.generate()];
+    const opts = { oracles: [heavy, light1, light2], weights: [3, 1, 1], quorumWeight: 3 };
+    const voteDelivery = (fx: Fixture, oracle: web3.Keypair) =>
+      pg.program.methods
+        .voteDelivery(Array(32).fill(0))
+        .accounts({ oracle: oracle.publicKey, escrow: fx.escrow })
+        .signers([oracle])
+        .rpc();
+
+    // Two light oracles: 2 of 3 weight
+    const light = await setupEscrow(opts);
+    await voteDelivery(light, light1);
+    const sig = await voteDelivery(light, light2);
+    const [tally] = await eventsOf(sig, "OracleVotesRecorded");
+    assert.equal(tally.liveVotes, 2);
+    assert.equal(tally.liveWeight, 2);
+    assert.equal((await eventsOf(sig, "DeliveryVerified")).length, 0);
+    let e = await pg.program.account.escrow.fetch(light.escrow);
+    assert.equal(e.state, 1 /* Open */);
+    assert.equal(e.verifiedTs.toNumber(), 0);
+
+    // The heavy oracle alone meets quorum_weight
+    const fx = await setupEscrow(opts);
+    const [verified] = await eventsOf(await voteDelivery(fx, heavy), "DeliveryVerified");
+    assert.ok(verified, "DeliveryVerified emitted");
+    assert.equal(verified.quorumVotes, 1);
+    assert.equal(verified.quorumWeight, 3);
+    e = await pg.program.account.escrow.fetch(fx.escrow);
+    assert.equal(e.state, 2 /* Verified */);
+    assert.ok(e.verifiedTs.toNumber() > 0);
+  });
 });